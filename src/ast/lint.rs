@@ -0,0 +1,276 @@
+use crate::ast::*;
+
+use std::collections::HashSet;
+
+/// A likely mistake found by `lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub message: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: warning: {}", self.span, self.message)
+    }
+}
+
+/// Finds values that may be null, because they come from a `.get(...)`
+/// call such as `dict.get`, used where null would fail: as the receiver of
+/// `.`, an operand of an operator other than `==`, `!=`, `??` and `in`, a
+/// condition or a callee.
+///
+/// Variables are followed in source order, without regard to control flow:
+/// one holding a `.get(...)` result counts as checked once it is compared
+/// with `null`, and is forgotten when reassigned.
+pub fn lint(program: &Program) -> Vec<Warning> {
+    let mut linter = Linter::default();
+    linter.statements(&program.statements);
+    linter.warnings
+}
+
+#[derive(Default)]
+struct Linter {
+    /// Variables holding an unchecked `.get(...)` result.
+    maybe_null: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+/// Whether `expr` is a `.get(...)` call, which returns null for a missing
+/// key.
+fn is_get_call(expr: &Expr) -> bool {
+    matches!(expr, Expr::Call { target, .. }
+        if matches!(target.as_ref(), Expr::Member { field, .. } if field == "get"))
+}
+
+impl Linter {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Declaration { target, value, .. } => {
+                self.expr(value);
+                self.bind(target, value);
+            }
+            Statement::Assignment { target, op, value } => {
+                self.expr(value);
+                match op {
+                    AssignOp::Assign => self.bind(target, value),
+                    _ => self.used(value, "an operator"),
+                }
+            }
+            Statement::FunctionDeclaration { function, .. } => self.expr(function),
+            Statement::Print(exprs) => exprs.iter().for_each(|e| self.expr(e)),
+            Statement::Return(expr) | Statement::Yield(expr) | Statement::Expression(expr) => {
+                self.expr(expr)
+            }
+            Statement::Throw { value, .. } => self.expr(value),
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.expr(body);
+                if let Some(catch) = catch {
+                    self.maybe_null.remove(&catch.name);
+                    self.expr(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.expr(finally);
+                }
+            }
+            Statement::If {
+                condition,
+                then_stmt,
+                else_stmt,
+            } => {
+                self.condition(condition);
+                self.expr(then_stmt);
+                if let Some(else_stmt) = else_stmt {
+                    self.expr(else_stmt);
+                }
+            }
+            Statement::While { condition, body } => {
+                self.condition(condition);
+                self.expr(body);
+            }
+            Statement::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                self.expr(body);
+                if let Some(update) = update {
+                    self.statement(update);
+                }
+            }
+        }
+    }
+
+    /// Records whether the variables `target` binds may now be null.
+    fn bind(&mut self, target: &AssignTarget, value: &Expr) {
+        match target {
+            AssignTarget::Identifier(name) if is_get_call(value) => {
+                self.maybe_null.insert(name.clone());
+            }
+            AssignTarget::Identifier(name) => {
+                self.maybe_null.remove(name);
+            }
+            AssignTarget::Tuple(targets) => {
+                for target in targets {
+                    self.bind(target, &Expr::Null);
+                }
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        self.expr(condition);
+        self.used(condition, "a condition");
+    }
+
+    /// Warns if `expr` may be null where it is used as `what`. Each
+    /// variable is reported once.
+    fn used(&mut self, expr: &Expr, what: &str) {
+        match expr {
+            Expr::Identifier { name, span } if self.maybe_null.remove(name) => {
+                self.warnings.push(Warning {
+                    message: format!(
+                        "'{name}' may be null (from `get`) when used in {what}; \
+                         check it with `!= null`, or use `??` or `?.`"
+                    ),
+                    span: *span,
+                });
+            }
+            Expr::Call { span, .. } if is_get_call(expr) => {
+                self.warnings.push(Warning {
+                    message: format!(
+                        "the result of `get` may be null when used in {what}; \
+                         use `??` or `?.`"
+                    ),
+                    span: *span,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(_) | Expr::String(_) | Expr::Boolean(_) | Expr::Null => {}
+            Expr::Identifier { .. } => {}
+            Expr::Tuple { values } | Expr::ListLiteral { values } | Expr::SetLiteral { values } => {
+                values.iter().for_each(|e| self.expr(e))
+            }
+            Expr::DictLiteral { entries } => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::ListComprehension { element, clause }
+            | Expr::SetComprehension { element, clause } => {
+                self.clause(clause);
+                self.expr(element);
+            }
+            Expr::DictComprehension { key, value, clause } => {
+                self.clause(clause);
+                self.expr(key);
+                self.expr(value);
+            }
+            Expr::BinaryOp {
+                op, left, right, ..
+            } => {
+                self.expr(left);
+                self.expr(right);
+                match op {
+                    BinOp::Eq | BinOp::Ne => {
+                        for (checked, other) in [(left, right), (right, left)] {
+                            if let (Expr::Identifier { name, .. }, Expr::Null) =
+                                (checked.as_ref(), other.as_ref())
+                            {
+                                self.maybe_null.remove(name);
+                            }
+                        }
+                    }
+                    BinOp::Coalesce => {}
+                    BinOp::In => self.used(right, "an operator"),
+                    _ => {
+                        self.used(left, "an operator");
+                        self.used(right, "an operator");
+                    }
+                }
+            }
+            Expr::UnaryOp { operand, .. } => {
+                self.expr(operand);
+                self.used(operand, "an operator");
+            }
+            Expr::Member {
+                target, optional, ..
+            } => {
+                self.expr(target);
+                if !optional {
+                    self.used(target, "a member access");
+                }
+            }
+            Expr::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                self.condition(condition);
+                self.expr(then);
+                self.expr(otherwise);
+            }
+            Expr::Call {
+                target,
+                args,
+                named,
+                ..
+            } => {
+                self.expr(target);
+                self.used(target, "a call");
+                args.iter().for_each(|e| self.expr(e));
+                named.iter().for_each(|(_, e)| self.expr(e));
+            }
+            Expr::Block(statements) => self.statements(statements),
+            Expr::Function {
+                arguments,
+                statement,
+                ..
+            } => {
+                // Parameters shadow the variables they are named after.
+                let outer = self.maybe_null.clone();
+                for param in arguments {
+                    if let Some(default) = &param.default {
+                        self.expr(default);
+                    }
+                    self.maybe_null.remove(&param.name);
+                }
+                self.expr(statement);
+                self.maybe_null = outer;
+            }
+        }
+    }
+
+    fn clause(&mut self, clause: &ComprehensionClause) {
+        self.expr(&clause.iterable);
+        self.used(&clause.iterable, "a comprehension");
+        self.bind(&clause.target, &Expr::Null);
+        if let Some(condition) = &clause.condition {
+            self.condition(condition);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod lint;
 pub mod parser;
 
 use serde::Serialize;
@@ -7,41 +8,103 @@ pub enum Expr {
     Number(i32),
     String(String),
     Boolean(bool),
+    Null,
 
     Tuple {
         values: Vec<Expr>,
     },
 
+    ListLiteral {
+        values: Vec<Expr>,
+    },
+
+    DictLiteral {
+        entries: Vec<(Expr, Expr)>,
+    },
+
+    SetLiteral {
+        values: Vec<Expr>,
+    },
+
+    ListComprehension {
+        element: Box<Expr>,
+        clause: Box<ComprehensionClause>,
+    },
+
+    DictComprehension {
+        key: Box<Expr>,
+        value: Box<Expr>,
+        clause: Box<ComprehensionClause>,
+    },
+
+    SetComprehension {
+        element: Box<Expr>,
+        clause: Box<ComprehensionClause>,
+    },
+
     BinaryOp {
         op: BinOp,
         left: Box<Expr>,
         right: Box<Expr>,
+        span: Span,
     },
 
     UnaryOp {
         op: UnOp,
         operand: Box<Expr>,
+        span: Span,
     },
 
-    Identifier(String),
+    Identifier {
+        name: String,
+        span: Span,
+    },
     Member {
         target: Box<Expr>,
         field: String,
+        /// `target?.field`, which is null when `target` is.
+        optional: bool,
+        span: Span,
     },
 
     Block(Vec<Statement>),
 
     Function {
-        arguments: Vec<AssignTarget>,
+        arguments: Vec<Param>,
         statement: Box<Expr>,
+        generator: bool,
+    },
+
+    /// `condition ? then : otherwise`
+    Conditional {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+        span: Span,
     },
 
     Call {
         target: Box<Expr>,
         args: Vec<Expr>,
+        /// `name: value` arguments, which follow the positional ones.
+        named: Vec<(String, Expr)>,
+        span: Span,
     },
 }
 
+/// A position in the source, 1-based.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub enum BinOp {
     // Arithmetic
@@ -52,6 +115,13 @@ pub enum BinOp {
     Mod,
     Pow,
 
+    // Bitwise, on ints
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+
     // Comparison
     Eq,
     Ne,
@@ -59,10 +129,14 @@ pub enum BinOp {
     Le,
     Gt,
     Ge,
+    /// `item in collection`
+    In,
 
     // Logical
     And,
     Or,
+    /// `left ?? right`: `right` only when `left` is null.
+    Coalesce,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -85,10 +159,30 @@ pub enum Statement {
         value: Expr,
     },
 
+    /// `fn name(...) { ... }`; `function` is the `Expr::Function`.
+    FunctionDeclaration {
+        name: String,
+        function: Box<Expr>,
+    },
+
     Print(Vec<Expr>),
 
     Return(Expr),
 
+    Yield(Expr),
+
+    /// Raises the value as an error; `span` locates the `throw`.
+    Throw {
+        value: Expr,
+        span: Span,
+    },
+
+    Try {
+        body: Box<Expr>,
+        catch: Option<CatchClause>,
+        finally: Option<Box<Expr>>,
+    },
+
     If {
         condition: Expr,
         then_stmt: Box<Expr>,
@@ -113,15 +207,112 @@ pub enum Statement {
 #[derive(Serialize, Debug, Clone)]
 pub enum AssignTarget {
     Identifier(String),
+    Tuple(Vec<AssignTarget>),
+}
+
+// `<name>`, `<name> = <default>` or `...<name>` in a function's parameter list
+#[derive(Serialize, Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub default: Option<Expr>,
+    pub rest: bool,
+}
+
+// `catch (<name>) { ... }` part of a try statement
+#[derive(Serialize, Debug, Clone)]
+pub struct CatchClause {
+    pub name: String,
+    pub body: Box<Expr>,
+}
+
+// `for <target> in <iterable> if <condition>` part of a comprehension
+#[derive(Serialize, Debug, Clone)]
+pub struct ComprehensionClause {
+    pub target: AssignTarget,
+    pub iterable: Expr,
+    pub condition: Option<Expr>,
 }
 
 // Assignment operators
 #[derive(Serialize, Debug, Clone)]
 pub enum AssignOp {
     Assign,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    ModAssign,
+}
+
+impl AssignOp {
+    /// The operator a compound assignment such as `+=` applies.
+    pub fn binary_op(&self) -> Option<BinOp> {
+        match self {
+            AssignOp::Assign => None,
+            AssignOp::AddAssign => Some(BinOp::Add),
+            AssignOp::SubAssign => Some(BinOp::Sub),
+            AssignOp::MulAssign => Some(BinOp::Mul),
+            AssignOp::DivAssign => Some(BinOp::Div),
+            AssignOp::ModAssign => Some(BinOp::Mod),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
+
+impl Statement {
+    /// Whether this statement yields, either directly or from a nested block.
+    /// Function literals are not searched, since they yield on their own.
+    pub fn contains_yield(&self) -> bool {
+        match self {
+            Statement::Yield(_) => true,
+            Statement::If {
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                then_stmt.contains_yield() || else_stmt.as_ref().is_some_and(|e| e.contains_yield())
+            }
+            Statement::While { body, .. } | Statement::For { body, .. } => body.contains_yield(),
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                body.contains_yield()
+                    || catch.as_ref().is_some_and(|c| c.body.contains_yield())
+                    || finally.as_ref().is_some_and(|f| f.contains_yield())
+            }
+            Statement::Expression(expr) => expr.contains_yield(),
+            _ => false,
+        }
+    }
+}
+
+impl Expr {
+    /// Where this expression is reported in errors. Only expressions that
+    /// can fail on their own carry a span: names, member accesses,
+    /// operators, conditionals and calls.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expr::Identifier { span, .. }
+            | Expr::Member { span, .. }
+            | Expr::BinaryOp { span, .. }
+            | Expr::UnaryOp { span, .. }
+            | Expr::Conditional { span, .. }
+            | Expr::Call { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Whether this expression is a block containing a `yield` statement.
+    pub fn contains_yield(&self) -> bool {
+        match self {
+            Expr::Block(statements) => statements.iter().any(Statement::contains_yield),
+            _ => false,
+        }
+    }
+}
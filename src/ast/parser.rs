@@ -16,16 +16,22 @@ lazy_static::lazy_static! {
 
         PrattParser::new()
             // Lowest precedence first
+            .op(Op::infix(pipe, Left)) // |>
+            .op(Op::infix(coalesce, Left)) // ??
             .op(Op::infix(logical_or, Left)) // ||
             .op(Op::infix(logical_and, Left)) // &&
-            .op(Op::infix(eq, Left) | Op::infix(ne, Left)) // == !=
+            .op(Op::infix(eq, Left) | Op::infix(ne, Left) | Op::infix(in_op, Left)) // == != in
             .op(Op::infix(le, Left) | Op::infix(ge, Left) | Op::infix(lt, Left) | Op::infix(gt, Left)) // <= >= < >
+            .op(Op::infix(bit_or, Left)) // |
+            .op(Op::infix(bit_xor, Left)) // xor
+            .op(Op::infix(bit_and, Left)) // &
+            .op(Op::infix(shl, Left) | Op::infix(shr, Left)) // << >>
             .op(Op::infix(add, Left) | Op::infix(subtract, Left))  // + -
             .op(Op::infix(multiply, Left) | Op::infix(divide, Left) | Op::infix(modulo, Left)) // * / %
             .op(Op::infix(power, Right))           // ^ or **
             // Highest precedence
             .op(Op::prefix(logical_not) | Op::prefix(unary_minus)) // ! -
-            .op(Op::postfix(member_access) | Op::postfix(call_suffix))
+            .op(Op::postfix(member_access) | Op::postfix(optional_member) | Op::postfix(call_suffix))
     };
 }
 
@@ -47,6 +53,36 @@ pub fn underline_error(input: &str, err: &pest::error::Error<Rule>) -> String {
     }
 }
 
+fn span_of(pair: &Pair<Rule>) -> Span {
+    let (line, column) = pair.as_span().start_pos().line_col();
+    Span { line, column }
+}
+
+/// Resolves the escapes in a string literal's contents. A backslash before
+/// any other character is kept as written.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c @ ('"' | '\\')) => out.push(c),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
 pub fn parse_program(input: &str) -> Result<Program> {
     let mut pairs = SludgeParser::parse(Rule::program, input)?;
     let program_pair = pairs.next().unwrap();
@@ -70,9 +106,40 @@ pub fn parse_stmt(
 }
 
 fn parse_exprs(pairs: Pairs<Rule>) -> Result<Expr> {
+    // A trailing `? then : otherwise` applies to all the operators before it.
+    let mut pairs: Vec<_> = pairs.collect();
+    let conditional = match pairs.last() {
+        Some(pair) if pair.as_rule() == Rule::conditional => pairs.pop(),
+        _ => None,
+    };
+    let condition = parse_operators(pairs)?;
+    let Some(conditional) = conditional else {
+        return Ok(condition);
+    };
+    let span = span_of(&conditional);
+    let mut inner = conditional.into_inner();
+    let then = inner
+        .next()
+        .ok_or_else(|| anyhow!("Missing then branch in conditional"))?;
+    let otherwise = inner
+        .next()
+        .ok_or_else(|| anyhow!("Missing else branch in conditional"))?;
+    Ok(Expr::Conditional {
+        condition: Box::new(condition),
+        then: Box::new(parse_expr(then)?),
+        otherwise: Box::new(parse_expr(otherwise)?),
+        span,
+    })
+}
+
+fn parse_operators(pairs: Vec<Pair<Rule>>) -> Result<Expr> {
     PRATT_PARSER
         .map_primary(parse_expr)
         .map_infix(|lhs, op, rhs| {
+            let span = span_of(&op);
+            if op.as_rule() == Rule::pipe {
+                return Ok(pipe(lhs?, rhs?, span));
+            }
             let bin_op = match op.as_rule() {
                 Rule::add => BinOp::Add,
                 Rule::subtract => BinOp::Sub,
@@ -88,15 +155,24 @@ fn parse_exprs(pairs: Pairs<Rule>) -> Result<Expr> {
                 Rule::gt => BinOp::Gt,
                 Rule::logical_and => BinOp::And,
                 Rule::logical_or => BinOp::Or,
+                Rule::coalesce => BinOp::Coalesce,
+                Rule::bit_and => BinOp::BitAnd,
+                Rule::bit_or => BinOp::BitOr,
+                Rule::bit_xor => BinOp::BitXor,
+                Rule::shl => BinOp::Shl,
+                Rule::shr => BinOp::Shr,
+                Rule::in_op => BinOp::In,
                 _ => return Err(anyhow!("Unexpected infix op: {:?}", op)),
             };
             Ok(Expr::BinaryOp {
                 op: bin_op,
                 left: Box::new(lhs?),
                 right: Box::new(rhs?),
+                span,
             })
         })
         .map_prefix(|op, rhs| {
+            let span = span_of(&op);
             let un_op = match op.as_rule() {
                 Rule::unary_minus => UnOp::Neg,
                 Rule::logical_not => UnOp::Not,
@@ -105,31 +181,61 @@ fn parse_exprs(pairs: Pairs<Rule>) -> Result<Expr> {
             Ok(Expr::UnaryOp {
                 op: un_op,
                 operand: Box::new(rhs?),
+                span,
             })
         })
         .map_postfix(|lhs, postfix| {
             let target = Box::new(lhs?);
             match postfix.as_rule() {
                 Rule::call_suffix => {
-                    let args = postfix
-                        .into_inner()
-                        .map(parse_expr)
-                        .collect::<Result<Vec<_>, _>>()?;
-                    Ok(Expr::Call { target, args })
+                    // Calls are reported at their callee, or at the
+                    // parenthesis when the callee has no position itself.
+                    let span = target.span().unwrap_or_else(|| span_of(&postfix));
+                    let mut args = Vec::new();
+                    let mut named = Vec::new();
+                    for arg in postfix.into_inner() {
+                        if arg.as_rule() == Rule::named_arg {
+                            let mut inner = arg.into_inner();
+                            let name = inner
+                                .next()
+                                .ok_or_else(|| anyhow!("Missing name in named argument"))?;
+                            let value = inner
+                                .next()
+                                .ok_or_else(|| anyhow!("Missing value in named argument"))?;
+                            named.push((name.as_str().to_string(), parse_expr(value)?));
+                        } else if named.is_empty() {
+                            args.push(parse_expr(arg)?);
+                        } else {
+                            return Err(anyhow!(
+                                "positional argument follows named argument at {}",
+                                span_of(&arg)
+                            ));
+                        }
+                    }
+                    Ok(Expr::Call {
+                        target,
+                        args,
+                        named,
+                        span,
+                    })
                 }
-                Rule::member_access => {
+                Rule::member_access | Rule::optional_member => {
+                    let optional = postfix.as_rule() == Rule::optional_member;
                     let field = postfix
                         .into_inner()
                         .next()
-                        .ok_or_else(|| anyhow!("Missing field name in member access"))?
-                        .as_str()
-                        .to_string();
-                    Ok(Expr::Member { target, field })
+                        .ok_or_else(|| anyhow!("Missing field name in member access"))?;
+                    Ok(Expr::Member {
+                        target,
+                        span: span_of(&field),
+                        field: field.as_str().to_string(),
+                        optional,
+                    })
                 }
                 _ => Err(anyhow!("Unexpected postfix: {:?}", postfix)),
             }
         })
-        .parse(pairs)
+        .parse(pairs.into_iter())
 }
 
 fn parse_expr(primary: Pair<Rule>) -> Result<Expr> {
@@ -143,31 +249,16 @@ fn parse_expr(primary: Pair<Rule>) -> Result<Expr> {
                 _ => Err(anyhow!("Invalid boolean literal: {}", text)),
             }
         }
+        Rule::null => Ok(Expr::Null),
         Rule::string => {
             let s = primary.as_str();
-            Ok(Expr::String(s[1..s.len() - 1].to_string()))
-        }
-        Rule::identifier => Ok(Expr::Identifier(primary.as_str().to_string())),
-        Rule::function_literal => {
-            let inner = primary.into_inner();
-            let mut arguments = Vec::new();
-            let mut statement: Option<Box<Expr>> = None;
-            for node in inner {
-                if node.as_rule() == Rule::param {
-                    arguments.push(AssignTarget::Identifier(node.as_str().to_string()));
-                } else if node.as_rule() == Rule::block {
-                    statement = Some(Box::new(parse_expr(node)?));
-                }
-            }
-
-            match statement {
-                Some(statement) => Ok(Expr::Function {
-                    arguments,
-                    statement,
-                }),
-                None => Err(anyhow!("Function literal missing body")),
-            }
+            Ok(Expr::String(unescape(&s[1..s.len() - 1])))
         }
+        Rule::identifier => Ok(Expr::Identifier {
+            name: primary.as_str().to_string(),
+            span: span_of(&primary),
+        }),
+        Rule::function_literal => parse_function(primary.into_inner()),
         Rule::tuple_expr => {
             let values = primary
                 .into_inner()
@@ -175,6 +266,68 @@ fn parse_expr(primary: Pair<Rule>) -> Result<Expr> {
                 .collect::<Result<Vec<_>>>()?;
             Ok(Expr::Tuple { values })
         }
+        Rule::list_comprehension | Rule::set_comprehension => {
+            let rule = primary.as_rule();
+            let mut inner = primary.into_inner();
+            let element = Box::new(parse_expr(
+                inner
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing element in comprehension"))?,
+            )?);
+            let clause =
+                Box::new(parse_comprehension_clause(inner.next().ok_or_else(
+                    || anyhow!("Missing 'for' clause in comprehension"),
+                )?)?);
+            if rule == Rule::list_comprehension {
+                Ok(Expr::ListComprehension { element, clause })
+            } else {
+                Ok(Expr::SetComprehension { element, clause })
+            }
+        }
+        Rule::dict_comprehension => {
+            let mut inner = primary.into_inner();
+            let key =
+                Box::new(parse_expr(inner.next().ok_or_else(|| {
+                    anyhow!("Missing key in dictionary comprehension")
+                })?)?);
+            let value =
+                Box::new(parse_expr(inner.next().ok_or_else(|| {
+                    anyhow!("Missing value in dictionary comprehension")
+                })?)?);
+            let clause =
+                Box::new(parse_comprehension_clause(inner.next().ok_or_else(
+                    || anyhow!("Missing 'for' clause in comprehension"),
+                )?)?);
+            Ok(Expr::DictComprehension { key, value, clause })
+        }
+        Rule::list_literal => {
+            let values = primary
+                .into_inner()
+                .map(parse_expr)
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Expr::ListLiteral { values })
+        }
+        Rule::set_literal => {
+            let values = primary
+                .into_inner()
+                .map(parse_expr)
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Expr::SetLiteral { values })
+        }
+        Rule::dict_literal => {
+            let mut entries = Vec::new();
+            for entry in primary.into_inner() {
+                let mut inner = entry.into_inner();
+                let key = inner
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing key in dictionary entry"))?;
+                let value = inner
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing value in dictionary entry"))?;
+                entries.push((parse_expr(key)?, parse_expr(value)?));
+            }
+            Ok(Expr::DictLiteral { entries })
+        }
         Rule::block => {
             let mut statements = Vec::new();
             for inner in primary.into_inner() {
@@ -187,6 +340,119 @@ fn parse_expr(primary: Pair<Rule>) -> Result<Expr> {
     }
 }
 
+/// Builds an `Expr::Function` from the parameters and body of a function
+/// literal or declaration.
+fn parse_function(inner: Pairs<Rule>) -> Result<Expr> {
+    let mut arguments = Vec::new();
+    let mut statement: Option<Box<Expr>> = None;
+    for node in inner {
+        if node.as_rule() == Rule::param {
+            if arguments.last().is_some_and(|p: &Param| p.rest) {
+                return Err(anyhow!("rest parameter must come last"));
+            }
+            arguments.push(parse_param(node)?);
+        } else if node.as_rule() == Rule::block {
+            statement = Some(Box::new(parse_expr(node)?));
+        }
+    }
+
+    match statement {
+        Some(statement) => Ok(Expr::Function {
+            arguments,
+            generator: statement.contains_yield(),
+            statement,
+        }),
+        None => Err(anyhow!("Function literal missing body")),
+    }
+}
+
+/// Desugars `value |> f` to `f(value)` and `value |> f(args)` to
+/// `f(value, args)`.
+fn pipe(value: Expr, callee: Expr, span: Span) -> Expr {
+    match callee {
+        Expr::Call {
+            target,
+            mut args,
+            named,
+            span,
+        } => {
+            args.insert(0, value);
+            Expr::Call {
+                target,
+                args,
+                named,
+                span,
+            }
+        }
+        target => Expr::Call {
+            span: target.span().unwrap_or(span),
+            target: Box::new(target),
+            args: vec![value],
+            named: Vec::new(),
+        },
+    }
+}
+
+fn parse_param(pair: Pair<Rule>) -> Result<Param> {
+    let mut param = Param {
+        name: String::new(),
+        default: None,
+        rest: false,
+    };
+    for node in pair.into_inner() {
+        match node.as_rule() {
+            Rule::rest_marker => param.rest = true,
+            Rule::identifier => param.name = node.as_str().to_string(),
+            Rule::type_expr => {}
+            _ => param.default = Some(parse_expr(node)?),
+        }
+    }
+    if param.rest && param.default.is_some() {
+        return Err(anyhow!(
+            "rest parameter '{}' cannot have a default",
+            param.name
+        ));
+    }
+    Ok(param)
+}
+
+fn parse_comprehension_clause(pair: Pair<Rule>) -> Result<ComprehensionClause> {
+    let mut inner = pair.into_inner();
+
+    let target_pair = inner
+        .next()
+        .ok_or_else(|| anyhow!("Missing target in comprehension"))?;
+    let mut names = target_pair
+        .into_inner()
+        .map(|name| AssignTarget::Identifier(name.as_str().to_string()))
+        .collect::<Vec<_>>();
+    let target = if names.len() == 1 {
+        names.remove(0)
+    } else {
+        AssignTarget::Tuple(names)
+    };
+
+    let iterable_pair = inner
+        .next()
+        .ok_or_else(|| anyhow!("Missing iterable in comprehension"))?;
+    let iterable = parse_expr(iterable_pair)
+        .map_err(|e| anyhow!("Failed to parse comprehension iterable: {}", e))?;
+
+    let condition = match inner.next() {
+        Some(cond_pair) => Some(
+            parse_exprs(cond_pair.into_inner())
+                .map_err(|e| anyhow!("Failed to parse comprehension condition: {}", e))?,
+        ),
+        None => None,
+    };
+
+    Ok(ComprehensionClause {
+        target,
+        iterable,
+        condition,
+    })
+}
+
 fn parse_statement(pair: Pair<Rule>) -> Result<Statement> {
     match pair.as_rule() {
         Rule::print_stmt => {
@@ -231,6 +497,11 @@ fn parse_statement(pair: Pair<Rule>) -> Result<Statement> {
 
             let op = match op_pair.as_rule() {
                 Rule::assign => AssignOp::Assign,
+                Rule::add_assign => AssignOp::AddAssign,
+                Rule::sub_assign => AssignOp::SubAssign,
+                Rule::mul_assign => AssignOp::MulAssign,
+                Rule::div_assign => AssignOp::DivAssign,
+                Rule::mod_assign => AssignOp::ModAssign,
                 other => {
                     return Err(anyhow!(
                         "Invalid assignment operator: expected '=', got {:?}",
@@ -283,6 +554,17 @@ fn parse_statement(pair: Pair<Rule>) -> Result<Statement> {
             Ok(Statement::Declaration { target, op, value })
         }
 
+        Rule::function_decl => {
+            let mut inner = pair.into_inner();
+            let name = inner
+                .next()
+                .ok_or_else(|| anyhow!("Missing name in function declaration"))?
+                .as_str()
+                .to_string();
+            let function = Box::new(parse_function(inner)?);
+            Ok(Statement::FunctionDeclaration { name, function })
+        }
+
         Rule::if_stmt => {
             let mut inner = pair.into_inner();
 
@@ -398,6 +680,68 @@ fn parse_statement(pair: Pair<Rule>) -> Result<Statement> {
                 .map_err(|e| anyhow!("Failed to parse return value: {}", e))?,
         )),
 
+        Rule::yield_stmt => Ok(Statement::Yield(
+            parse_exprs(pair.into_inner())
+                .map_err(|e| anyhow!("Failed to parse yield value: {}", e))?,
+        )),
+
+        Rule::throw_stmt => {
+            let span = span_of(&pair);
+            let value = parse_exprs(pair.into_inner())
+                .map_err(|e| anyhow!("Failed to parse thrown value: {}", e))?;
+            Ok(Statement::Throw { value, span })
+        }
+
+        Rule::try_stmt => {
+            let mut inner = pair.into_inner();
+            let body = Box::new(parse_expr(
+                inner
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing body in try statement"))?,
+            )?);
+
+            let mut catch = None;
+            let mut finally = None;
+            for clause in inner {
+                match clause.as_rule() {
+                    Rule::catch_clause => {
+                        let mut parts = clause.into_inner();
+                        let name = parts
+                            .next()
+                            .ok_or_else(|| anyhow!("Missing name in catch clause"))?
+                            .as_str()
+                            .to_string();
+                        let body = Box::new(parse_expr(
+                            parts
+                                .next()
+                                .ok_or_else(|| anyhow!("Missing body in catch clause"))?,
+                        )?);
+                        catch = Some(CatchClause { name, body });
+                    }
+                    Rule::finally_clause => {
+                        finally = Some(Box::new(parse_expr(
+                            clause
+                                .into_inner()
+                                .next()
+                                .ok_or_else(|| anyhow!("Missing body in finally clause"))?,
+                        )?));
+                    }
+                    other => {
+                        return Err(anyhow!("Unexpected element in try statement: {:?}", other));
+                    }
+                }
+            }
+
+            if catch.is_none() && finally.is_none() {
+                return Err(anyhow!("try statement needs a catch or finally block"));
+            }
+            Ok(Statement::Try {
+                body,
+                catch,
+                finally,
+            })
+        }
+
         Rule::expr_stmt => Ok(Statement::Expression(
             parse_exprs(pair.into_inner())
                 .map_err(|e| anyhow!("Failed to parse expression statement: {}", e))?,
@@ -0,0 +1,452 @@
+use crate::ast::Program;
+use crate::ast::parser::parse_program;
+use crate::interpreter::Interpreter;
+use crate::interpreter::builtins;
+use crate::interpreter::builtins::fs::Permissions;
+use crate::interpreter::convert::{HostBuiltin, HostFn, RawHostBuiltin};
+use crate::interpreter::limits::{CancellationHandle, Limits};
+use crate::interpreter::signature::{Signature, SignedBuiltin};
+use crate::interpreter::value::Value;
+use crate::interpreter::variable_scope::VariableScope;
+
+use anyhow::{Result, anyhow};
+use std::cell::RefCell;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// A group of builtins a host can make available to scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinModule {
+    /// Collection constructors (`list`, `dict`, `from_items`, `set`,
+    /// `range`), `error`, `compose` and `partial`.
+    Core,
+    /// `args`, `env`, `read_line`, `read_stdin` and `exit`.
+    Sys,
+    /// The `json` module with `parse` and `stringify`.
+    Json,
+    /// File access, limited to the roots granted through `permissions`.
+    Fs,
+    /// `exec` and `spawn`, only runnable once `allow_run` is set.
+    Process,
+}
+
+impl BuiltinModule {
+    pub const ALL: [BuiltinModule; 5] = [
+        BuiltinModule::Core,
+        BuiltinModule::Sys,
+        BuiltinModule::Json,
+        BuiltinModule::Fs,
+        BuiltinModule::Process,
+    ];
+}
+
+/// Configures an [`Engine`]. By default every builtin module is declared,
+/// with filesystem and subprocess access denied.
+pub struct EngineBuilder {
+    modules: Vec<BuiltinModule>,
+    args: Vec<String>,
+    permissions: Permissions,
+    allow_run: bool,
+    stdout: Rc<RefCell<dyn Write>>,
+    limits: Limits,
+}
+
+impl EngineBuilder {
+    /// Declares only the given builtin modules.
+    pub fn modules(mut self, modules: &[BuiltinModule]) -> Self {
+        self.modules = modules.to_vec();
+        self
+    }
+
+    /// Sets the values returned by `args()`.
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Grants the filesystem builtins access to `permissions`.
+    pub fn permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Allows `exec` and `spawn` to run subprocesses.
+    pub fn allow_run(mut self, allow_run: bool) -> Self {
+        self.allow_run = allow_run;
+        self
+    }
+
+    /// Sends `print` output to `stdout` instead of the process stdout.
+    pub fn stdout(mut self, stdout: Rc<RefCell<dyn Write>>) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    /// Bounds the resources each `eval` may use.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn build(mut self) -> Engine {
+        let scope = VariableScope::root();
+        for module in std::mem::take(&mut self.modules) {
+            match module {
+                BuiltinModule::Core => builtins::register_core(&scope),
+                BuiltinModule::Sys => {
+                    builtins::sys::register(&scope, std::mem::take(&mut self.args))
+                }
+                BuiltinModule::Json => builtins::json::register(&scope),
+                BuiltinModule::Fs => {
+                    builtins::fs::register(&scope, std::mem::take(&mut self.permissions))
+                }
+                BuiltinModule::Process => builtins::process::register(&scope, self.allow_run),
+            }
+        }
+        Engine {
+            interpreter: Interpreter::with_limits(scope, self.stdout, self.limits),
+        }
+    }
+}
+
+/// Embeds a Sludge interpreter. Globals persist across calls to `eval`, so
+/// a host can define functions once and call them repeatedly.
+#[derive(Debug)]
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// Creates an engine with the default builtin modules.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder {
+            modules: BuiltinModule::ALL.to_vec(),
+            args: Vec::new(),
+            permissions: Permissions::default(),
+            allow_run: false,
+            stdout: Rc::new(RefCell::new(std::io::stdout())),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Parses and runs `source`, returning the value of its last statement.
+    pub fn eval(&self, source: &str) -> Result<Value> {
+        let program = parse_program(source).map_err(|e| anyhow!("Parse error: {}", e))?;
+        self.run_program(&program)
+    }
+
+    /// Runs an already parsed program, returning the value of its last
+    /// statement, or of a top-level `return`, which stops the program.
+    pub fn run_program(&self, program: &Program) -> Result<Value> {
+        self.interpreter.reset_budget();
+        self.interpreter.hoist(&program.statements)?;
+        let mut last = Value::Null;
+        for stmt in &program.statements {
+            match self.interpreter.execute_statement(stmt)? {
+                Value::Return { value } => return Ok(*value),
+                value => last = value,
+            }
+        }
+        Ok(last)
+    }
+
+    /// Declares or overwrites a global variable.
+    pub fn set_global(&self, name: &str, value: Value) {
+        self.interpreter.variables.declare(name.to_string(), value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter.variables.get(name)
+    }
+
+    /// Declares a global function implemented in Rust. Arguments are
+    /// converted with `FromValue` and the result with `IntoValue`; calls with
+    /// the wrong number or type of arguments fail with an error naming the
+    /// function.
+    pub fn register_fn<Args: 'static, F: HostFn<Args>>(&self, name: &str, f: F) {
+        let builtin = HostBuiltin {
+            name: name.to_string(),
+            f,
+            args: PhantomData,
+        };
+        self.set_global(name, Value::BuiltinFn(Rc::new(builtin)));
+    }
+
+    /// Declares a global function that receives its arguments as raw values,
+    /// for variadic functions or ones accepting several types.
+    pub fn register_raw_fn<F>(&self, name: &str, f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value> + 'static,
+    {
+        let builtin = RawHostBuiltin {
+            name: name.to_string(),
+            f,
+        };
+        self.set_global(name, Value::BuiltinFn(Rc::new(builtin)));
+    }
+
+    /// Declares a global function with raw arguments and the parameters it
+    /// accepts, given as in `Signature::new`. Calls are checked against
+    /// them and may pass arguments by name; left out optional arguments
+    /// arrive as `null`, or not at all when trailing.
+    pub fn register_fn_with_signature<F>(&self, name: &str, params: &[&str], f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value> + 'static,
+    {
+        let builtin = SignedBuiltin {
+            name: name.to_string(),
+            signature: Signature::new(params),
+            f,
+        };
+        self.set_global(name, Value::BuiltinFn(Rc::new(builtin)));
+    }
+
+    /// Calls the global function `name` with `args`.
+    pub fn call_function(&self, name: &str, args: &[Value]) -> Result<Value> {
+        self.interpreter.reset_budget();
+        let callable = self
+            .get_global(name)
+            .ok_or_else(|| anyhow!("call_function: '{name}' is not defined"))?;
+        self.interpreter.call_value(&callable, args)
+    }
+
+    /// A handle that aborts the running `eval` or `call_function` with
+    /// `LimitError::Interrupted`, from any thread.
+    pub fn cancellation_handle(&self) -> CancellationHandle {
+        self.interpreter.cancellation_handle()
+    }
+
+    /// The underlying interpreter, for hosts that drive statements directly
+    /// (such as a REPL).
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::error::{ErrorKind, SludgeError};
+    use crate::interpreter::value::NativeObject;
+    use anyhow::bail;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_eval_and_globals() -> Result<()> {
+        let engine = Engine::new();
+        assert_eq!(engine.eval("1 + 2")?, Value::Int32(3));
+
+        engine.set_global("base", Value::Int32(10));
+        engine.eval("let add = fn(x) { return x + base }")?;
+        assert_eq!(
+            engine.call_function("add", &[Value::Int32(5)])?,
+            Value::Int32(15)
+        );
+
+        engine.eval("base = 20")?;
+        assert_eq!(engine.get_global("base"), Some(Value::Int32(20)));
+        assert_eq!(engine.get_global("missing"), None);
+
+        let err = engine.call_function("missing", &[]).unwrap_err();
+        assert!(err.to_string().contains("'missing' is not defined"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_level_return() -> Result<()> {
+        let engine = Engine::new();
+        assert_eq!(engine.eval("return 5")?, Value::Int32(5));
+        assert_eq!(
+            engine.eval("let x = 1\nif (true) { return x + 1 }\nx = 10")?,
+            Value::Int32(2)
+        );
+        assert_eq!(engine.get_global("x"), Some(Value::Int32(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_fn_converts_arguments() -> Result<()> {
+        let engine = Engine::new();
+        engine.register_fn("now_ms", || 1234);
+        engine.register_fn("total", |xs: Vec<i32>| xs.iter().sum::<i32>());
+        engine.register_fn("lookup", |d: HashMap<String, i32>, key: String| {
+            d.get(&key).copied()
+        });
+        engine.register_fn("checked_div", |a: i32, b: i32| {
+            if b == 0 {
+                bail!("cannot divide {a} by zero");
+            }
+            Ok(a / b)
+        });
+        engine.register_raw_fn("count", |args| Ok(Value::Int32(args.len() as i32)));
+
+        assert_eq!(engine.eval("now_ms()")?, Value::Int32(1234));
+        assert_eq!(engine.eval("total([1, 2, 3])")?, Value::Int32(6));
+        assert_eq!(engine.eval(r#"lookup({"a": 1}, "a")"#)?, Value::Int32(1));
+        assert_eq!(engine.eval(r#"lookup({"a": 1}, "b")"#)?, Value::Null);
+        assert_eq!(engine.eval("count(1, 2, 3)")?, Value::Int32(3));
+        assert_eq!(
+            engine
+                .eval("[6, 8].map(fn(x) { return checked_div(x, 2) })")?
+                .to_string(),
+            "list(3, 4)"
+        );
+
+        let err = engine.eval("now_ms(1)").unwrap_err();
+        assert_eq!(err.to_string(), "now_ms: expected 0 argument(s), got 1");
+        assert_eq!(
+            SludgeError::find(&err).map(|e| e.kind),
+            Some(ErrorKind::ArityError)
+        );
+
+        let err = engine.eval(r#"total([1, "x"])"#).unwrap_err().to_string();
+        assert_eq!(err, r#"total: argument 0: expected int, got "x""#);
+        assert_eq!(
+            engine.eval(
+                r#"
+                    let message = null
+                    try { total("x") } catch (e) { message = e.message }
+                    message
+                "#
+            )?,
+            Value::String(r#"total: argument 0: expected list, got "x""#.to_string())
+        );
+
+        // Untyped host errors are raised as `RuntimeError`s with a location.
+        let err = engine.eval("\n  checked_div(1, 0)").unwrap_err();
+        assert_eq!(err.to_string(), "checked_div: cannot divide 1 by zero");
+        let error = SludgeError::find(&err).unwrap();
+        assert_eq!(error.kind, ErrorKind::RuntimeError);
+        assert_eq!(error.traceback("host"), "  at host:2:3");
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_fn_with_signature_binds_named_arguments() -> Result<()> {
+        let engine = Engine::new();
+        engine.register_fn_with_signature("greet", &["name", "greeting?"], |args| {
+            let greeting = match args.get(1) {
+                Some(Value::String(greeting)) => greeting.clone(),
+                _ => "hello".to_string(),
+            };
+            Ok(Value::String(format!("{greeting} {}", args[0])))
+        });
+
+        assert_eq!(
+            engine.eval(r#"greet("bob")"#)?,
+            Value::String("hello bob".to_string())
+        );
+        assert_eq!(
+            engine.eval(r#"greet(greeting: "hi", name: "amy")"#)?,
+            Value::String("hi amy".to_string())
+        );
+
+        let err = engine.eval("greet()").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "greet(name, greeting?): missing argument 'name'"
+        );
+        assert_eq!(
+            SludgeError::find(&err).map(|e| e.kind),
+            Some(ErrorKind::ArityError)
+        );
+
+        // Functions without a signature take positional arguments only.
+        engine.register_fn("now_ms", || 1234);
+        let err = engine.eval("now_ms(at: 1)").unwrap_err();
+        assert_eq!(err.to_string(), "unexpected named argument 'at'");
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct Counter {
+        label: String,
+        count: RefCell<i32>,
+    }
+
+    impl NativeObject for Counter {
+        fn type_name(&self) -> &str {
+            "Counter"
+        }
+
+        fn get_member(&self, field: &str) -> Option<Value> {
+            match field {
+                "label" => Some(Value::String(self.label.clone())),
+                _ => None,
+            }
+        }
+
+        fn call_method(&self, method: &str, args: &[Value]) -> Result<Value> {
+            match (method, args) {
+                ("add", [Value::Int32(n)]) => {
+                    *self.count.borrow_mut() += n;
+                    Ok(Value::Int32(*self.count.borrow()))
+                }
+                ("add", _) => bail!("Counter.add: expected one int"),
+                (other, _) => bail!("Counter has no method '{other}'"),
+            }
+        }
+
+        fn equals(&self, other: &dyn NativeObject) -> bool {
+            (other as &dyn std::any::Any)
+                .downcast_ref::<Counter>()
+                .is_some_and(|other| other.label == self.label)
+        }
+    }
+
+    fn counter(label: &str) -> Value {
+        Value::Native(Rc::new(Counter {
+            label: label.to_string(),
+            count: RefCell::new(0),
+        }))
+    }
+
+    #[test]
+    fn test_native_objects() -> Result<()> {
+        let engine = Engine::new();
+        engine.set_global("hits", counter("hits"));
+        engine.set_global("other_hits", counter("hits"));
+        engine.set_global("misses", counter("misses"));
+
+        engine.eval("hits.add(2)")?;
+        assert_eq!(engine.eval("hits.add(3)")?, Value::Int32(5));
+        assert_eq!(
+            engine.eval("hits.label")?,
+            Value::String("hits".to_string())
+        );
+        assert_eq!(engine.eval("hits == other_hits")?, Value::Boolean(true));
+        assert_eq!(engine.eval("hits == misses")?, Value::Boolean(false));
+        assert_eq!(engine.get_global("hits").unwrap().to_string(), "<Counter>");
+
+        let err = engine.eval("hits.reset()").unwrap_err().to_string();
+        assert_eq!(err, "Counter has no method 'reset'");
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_selects_modules_and_output() -> Result<()> {
+        let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+        let engine = Engine::builder()
+            .modules(&[BuiltinModule::Core])
+            .stdout(buffer.clone())
+            .build();
+
+        engine.eval("print(list(1, 2))")?;
+        assert_eq!(String::from_utf8(buffer.borrow().to_vec())?, "list(1, 2)\n");
+
+        assert_eq!(engine.get_global("json"), None);
+        assert!(engine.eval("json.stringify(1)").is_err());
+        Ok(())
+    }
+}
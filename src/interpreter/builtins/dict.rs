@@ -1,6 +1,9 @@
+use crate::interpreter::Interpreter;
+use crate::interpreter::builtins::{call_callback, expect_callable};
+use crate::interpreter::error::raise;
 use crate::interpreter::value::{Hashable, Value};
 
-use anyhow::{Context, Error, Result, bail};
+use anyhow::{Context, Error, Result};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -8,13 +11,20 @@ use std::rc::Rc;
 fn expect_dict(this: &Value, fname: &str) -> Result<Rc<RefCell<HashMap<Hashable, Value>>>> {
     match this {
         Value::Dictionary { values } => Ok(values.clone()),
-        other => bail!("{fname}: receiver is not a dictionary (got {other})"),
+        other => raise!(
+            TypeError,
+            "{fname}: receiver is not a dictionary (got {other})"
+        ),
     }
 }
 
 fn expect_n_args(args: &[Value], n: usize, fname: &str) -> Result<()> {
     if args.len() != n {
-        bail!("{fname}: expected {n} argument(s), got {}", args.len());
+        raise!(
+            ArityError,
+            "{fname}: expected {n} argument(s), got {}",
+            args.len()
+        );
     }
     Ok(())
 }
@@ -26,11 +36,25 @@ fn expect_hashable_key(v: &Value, fname: &str) -> Result<Hashable> {
 fn expect_tuple2(v: &Value, fname: &str) -> Result<(Value, Value)> {
     match v {
         Value::Tuple { values } if values.len() == 2 => Ok((values[0].clone(), values[1].clone())),
-        Value::Tuple { values } => bail!(
+        Value::Tuple { values } => raise!(
+            ValueError,
             "{fname}: expected Tuple of length 2, got length {}",
             values.len()
         ),
-        other => bail!("{fname}: expected Tuple(key, value), got {other}"),
+        other => raise!(
+            TypeError,
+            "{fname}: expected Tuple(key, value), got {other}"
+        ),
+    }
+}
+
+fn expect_dict_arg(arg: &Value, fname: &str) -> Result<Rc<RefCell<HashMap<Hashable, Value>>>> {
+    match arg {
+        Value::Dictionary { values } => Ok(values.clone()),
+        other => raise!(
+            TypeError,
+            "{fname}: argument must be a dictionary (got {other})"
+        ),
     }
 }
 
@@ -79,7 +103,7 @@ pub fn remove(this: &Value, args: &[Value]) -> Result<Value, Error> {
     let key = expect_hashable_key(&args[0], "remove")?;
     match values.borrow_mut().remove(&key) {
         Some(v) => Ok(v),
-        None => Ok(Value::Null),
+        None => raise!(KeyError, "remove: key {key:#} is not in the dictionary"),
     }
 }
 
@@ -121,3 +145,94 @@ pub fn length(this: &Value, _args: &[Value]) -> Result<Value, Error> {
     let values = expect_dict(this, "length")?;
     Ok(Value::Int32(values.borrow().len() as i32))
 }
+
+pub fn from_items(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "from_items")?;
+    let items = match &args[0] {
+        Value::List { values } => values.borrow().clone(),
+        other => raise!(
+            TypeError,
+            "from_items: argument must be a list of (key, value) tuples (got {other})"
+        ),
+    };
+    dict(&Value::Null, &items).with_context(|| "from_items: invalid item list")
+}
+
+pub fn has(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "has")?;
+    let values = expect_dict(this, "has")?;
+
+    let key = expect_hashable_key(&args[0], "has")?;
+    Ok(Value::Boolean(values.borrow().contains_key(&key)))
+}
+
+pub fn get_or(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 2, "get_or")?;
+    let values = expect_dict(this, "get_or")?;
+
+    let key = expect_hashable_key(&args[0], "get_or")?;
+    match values.borrow().get(&key) {
+        Some(v) => Ok(v.clone()),
+        None => Ok(args[1].clone()),
+    }
+}
+
+pub fn setdefault(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 2, "setdefault")?;
+    let values = expect_dict(this, "setdefault")?;
+
+    let key = expect_hashable_key(&args[0], "setdefault")?;
+    Ok(values
+        .borrow_mut()
+        .entry(key)
+        .or_insert_with(|| args[1].clone())
+        .clone())
+}
+
+pub fn merge(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let values = expect_dict(this, "merge")?;
+
+    let mut out = values.borrow().clone();
+    for arg in args {
+        let other = expect_dict_arg(arg, "merge")?;
+        out.extend(other.borrow().iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    Ok(Value::Dictionary {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn map_values(
+    interpreter: Rc<Interpreter>,
+    this: &Value,
+    args: &[Value],
+) -> Result<Value, Error> {
+    expect_n_args(args, 1, "map_values")?;
+    let f = expect_callable(args, 0, "map_values")?;
+    let entries = expect_dict(this, "map_values")?.borrow().clone();
+
+    let mut out = HashMap::with_capacity(entries.len());
+    for (k, v) in entries {
+        let mapped = call_callback(&interpreter, f, &v, k.as_value(), "map_values")?;
+        out.insert(k, mapped);
+    }
+    Ok(Value::Dictionary {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn filter(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "filter")?;
+    let f = expect_callable(args, 0, "filter")?;
+    let entries = expect_dict(this, "filter")?.borrow().clone();
+
+    let mut out = HashMap::new();
+    for (k, v) in entries {
+        if call_callback(&interpreter, f, &v, k.as_value(), "filter")?.to_bool()? {
+            out.insert(k, v);
+        }
+    }
+    Ok(Value::Dictionary {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
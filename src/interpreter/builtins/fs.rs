@@ -0,0 +1,255 @@
+use crate::interpreter::error::raise;
+use crate::interpreter::value::{BuiltinFn, Value};
+use crate::interpreter::variable_scope::VariableScope;
+
+use anyhow::{Context, Error, Result, anyhow, bail};
+use std::cell::RefCell;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Filesystem roots a script has been granted access to. Nothing is granted
+/// by default; hosts opt in per directory (e.g. `--allow-read=./data`).
+#[derive(Debug, Default)]
+pub struct Permissions {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Access {
+    Read,
+    Write,
+}
+
+impl Permissions {
+    /// Grants access below the given roots, which must already exist.
+    pub fn new(read: &[PathBuf], write: &[PathBuf]) -> Result<Self> {
+        let canonical = |roots: &[PathBuf]| {
+            roots
+                .iter()
+                .map(|root| {
+                    root.canonicalize()
+                        .with_context(|| format!("cannot grant access to '{}'", root.display()))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            read: canonical(read)?,
+            write: canonical(write)?,
+        })
+    }
+
+    /// Resolves `path` and checks it lies within a root granted for `access`.
+    fn check(&self, path: &str, access: Access, fname: &str) -> Result<PathBuf> {
+        let resolved =
+            resolve(Path::new(path)).with_context(|| format!("{fname}: invalid path '{path}'"))?;
+        let (roots, flag) = match access {
+            Access::Read => (&self.read, "--allow-read"),
+            Access::Write => (&self.write, "--allow-write"),
+        };
+        if roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            raise!(
+                PermissionError,
+                "{fname}: permission denied: {} access to '{path}' is not granted (use {flag})",
+                match access {
+                    Access::Read => "read",
+                    Access::Write => "write",
+                }
+            )
+        }
+    }
+}
+
+/// How many symlinks `resolve` follows by hand before giving up, as the OS
+/// does with `ELOOP`.
+const MAX_SYMLINKS: usize = 40;
+
+/// Makes `path` absolute and resolves symlinks through its longest existing
+/// ancestor, so a path that does not exist yet can still be checked. A
+/// dangling symlink counts as existing and is followed to its target, since
+/// writing through it would create that target.
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let mut path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+
+    for _ in 0..MAX_SYMLINKS {
+        let mut existing = path.as_path();
+        let mut rest = Vec::new();
+        while existing.symlink_metadata().is_err() {
+            rest.push(
+                existing
+                    .file_name()
+                    .ok_or_else(|| anyhow!("path cannot be resolved"))?
+                    .to_os_string(),
+            );
+            existing = existing
+                .parent()
+                .ok_or_else(|| anyhow!("path has no existing ancestor"))?;
+        }
+
+        if !existing.exists() {
+            // A dangling symlink: carry on from its target, relative to the
+            // directory holding the link.
+            let target = fs::read_link(existing)?;
+            let mut next = existing.parent().unwrap_or(existing).join(target);
+            next.extend(rest.iter().rev());
+            path = next;
+            continue;
+        }
+
+        // Only plain names are collected above: `..` below a missing directory
+        // has no file name, so it is rejected rather than resolved lexically.
+        let mut resolved = existing.canonicalize()?;
+        resolved.extend(rest.iter().rev());
+        return Ok(resolved);
+    }
+    bail!("too many levels of symbolic links")
+}
+
+type FsFn = fn(&Permissions, &[Value]) -> Result<Value, Error>;
+
+pub struct FsBuiltin {
+    pub name: &'static str,
+    pub permissions: Rc<Permissions>,
+    pub f: FsFn,
+}
+
+impl std::fmt::Debug for FsBuiltin {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_tuple("Builtin").field(&self.name).finish()
+    }
+}
+
+impl BuiltinFn for FsBuiltin {
+    fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        (self.f)(&self.permissions, args)
+    }
+}
+
+/// Declares the filesystem builtins in `scope`, bound to `permissions`.
+pub fn register(scope: &VariableScope, permissions: Permissions) {
+    let permissions = Rc::new(permissions);
+    let builtins: [(&'static str, FsFn); 7] = [
+        ("read_file", read_file),
+        ("write_file", write_file),
+        ("append_file", append_file),
+        ("list_dir", list_dir),
+        ("exists", exists),
+        ("remove_file", remove_file),
+        ("mkdir", mkdir),
+    ];
+    for (name, f) in builtins {
+        scope.declare(
+            name.to_string(),
+            Value::BuiltinFn(Rc::new(FsBuiltin {
+                name,
+                permissions: permissions.clone(),
+                f,
+            })),
+        );
+    }
+}
+
+fn expect_n_args(args: &[Value], n: usize, fname: &str) -> Result<()> {
+    if args.len() != n {
+        raise!(
+            ArityError,
+            "{fname}: expected {n} argument(s), got {}",
+            args.len()
+        );
+    }
+    Ok(())
+}
+
+fn expect_string<'a>(args: &'a [Value], idx: usize, fname: &str) -> Result<&'a str> {
+    match args.get(idx) {
+        Some(Value::String(s)) => Ok(s),
+        Some(other) => raise!(
+            TypeError,
+            "{fname}: argument {idx} must be a string (got {other})"
+        ),
+        None => raise!(ArityError, "{fname}: missing argument at position {idx}"),
+    }
+}
+
+pub fn read_file(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "read_file")?;
+    let path = expect_string(args, 0, "read_file")?;
+    let resolved = permissions.check(path, Access::Read, "read_file")?;
+    let contents = fs::read_to_string(resolved)
+        .with_context(|| format!("read_file: failed to read '{path}'"))?;
+    Ok(Value::String(contents))
+}
+
+pub fn write_file(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 2, "write_file")?;
+    let path = expect_string(args, 0, "write_file")?;
+    let contents = expect_string(args, 1, "write_file")?;
+    let resolved = permissions.check(path, Access::Write, "write_file")?;
+    fs::write(resolved, contents)
+        .with_context(|| format!("write_file: failed to write '{path}'"))?;
+    Ok(Value::Null)
+}
+
+pub fn append_file(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 2, "append_file")?;
+    let path = expect_string(args, 0, "append_file")?;
+    let contents = expect_string(args, 1, "append_file")?;
+    let resolved = permissions.check(path, Access::Write, "append_file")?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(resolved)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .with_context(|| format!("append_file: failed to append to '{path}'"))?;
+    Ok(Value::Null)
+}
+
+pub fn list_dir(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "list_dir")?;
+    let path = expect_string(args, 0, "list_dir")?;
+    let resolved = permissions.check(path, Access::Read, "list_dir")?;
+
+    let mut names = fs::read_dir(resolved)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<std::io::Result<Vec<_>>>()
+        })
+        .with_context(|| format!("list_dir: failed to list '{path}'"))?;
+    names.sort();
+
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(names.into_iter().map(Value::String).collect())),
+    })
+}
+
+pub fn exists(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "exists")?;
+    let path = expect_string(args, 0, "exists")?;
+    let resolved = permissions.check(path, Access::Read, "exists")?;
+    Ok(Value::Boolean(resolved.exists()))
+}
+
+pub fn remove_file(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "remove_file")?;
+    let path = expect_string(args, 0, "remove_file")?;
+    let resolved = permissions.check(path, Access::Write, "remove_file")?;
+    fs::remove_file(resolved).with_context(|| format!("remove_file: failed to remove '{path}'"))?;
+    Ok(Value::Null)
+}
+
+pub fn mkdir(permissions: &Permissions, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "mkdir")?;
+    let path = expect_string(args, 0, "mkdir")?;
+    let resolved = permissions.check(path, Access::Write, "mkdir")?;
+    fs::create_dir_all(resolved).with_context(|| format!("mkdir: failed to create '{path}'"))?;
+    Ok(Value::Null)
+}
@@ -0,0 +1,76 @@
+use crate::interpreter::Interpreter;
+use crate::interpreter::builtins::expect_callable;
+use crate::interpreter::error::raise;
+use crate::interpreter::signature::NamedArgs;
+use crate::interpreter::value::{BuiltinFn, Value};
+
+use anyhow::{Error, Result};
+use std::rc::Rc;
+
+/// `compose(f, g)`: a function passing its arguments to `g` and the result
+/// to `f`, so `compose(f, g)(x)` is `f(g(x))`.
+pub fn compose(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let outer = expect_callable(args, 0, "compose")?.clone();
+    let inner = expect_callable(args, 1, "compose")?.clone();
+    Ok(Value::BuiltinFn(Rc::new(Composed { outer, inner })))
+}
+
+/// `partial(f, args...)`: a function calling `f` with `args` followed by
+/// its own arguments.
+pub fn partial(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let callable = expect_callable(args, 0, "partial")?.clone();
+    Ok(Value::BuiltinFn(Rc::new(Partial {
+        callable,
+        args: args[1..].to_vec(),
+    })))
+}
+
+#[derive(Debug)]
+struct Composed {
+    outer: Value,
+    inner: Value,
+}
+
+impl BuiltinFn for Composed {
+    fn call(&self, _args: &[Value]) -> Result<Value, Error> {
+        raise!(
+            RuntimeError,
+            "compose: composed functions can only be called from a script"
+        )
+    }
+
+    fn call_in(
+        &self,
+        interpreter: &Interpreter,
+        args: &[Value],
+        named: NamedArgs,
+    ) -> Result<Value> {
+        let value = interpreter.call_with_named(&self.inner, args.to_vec(), named)?;
+        interpreter.call_value(&self.outer, &[value])
+    }
+}
+
+#[derive(Debug)]
+struct Partial {
+    callable: Value,
+    args: Vec<Value>,
+}
+
+impl BuiltinFn for Partial {
+    fn call(&self, _args: &[Value]) -> Result<Value, Error> {
+        raise!(
+            RuntimeError,
+            "partial: partial functions can only be called from a script"
+        )
+    }
+
+    fn call_in(
+        &self,
+        interpreter: &Interpreter,
+        args: &[Value],
+        named: NamedArgs,
+    ) -> Result<Value> {
+        let args = self.args.iter().chain(args).cloned().collect();
+        interpreter.call_with_named(&self.callable, args, named)
+    }
+}
@@ -0,0 +1,387 @@
+use crate::interpreter::Interpreter;
+use crate::interpreter::builtins::{call_callback, call_function, expect_callable};
+use crate::interpreter::error::{raise, sludge_error};
+use crate::interpreter::value::{Value, ValueIterator};
+
+use anyhow::{Error, Result};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type SharedIterator = Rc<RefCell<dyn ValueIterator>>;
+
+fn expect_iterator(this: &Value, fname: &str) -> Result<SharedIterator> {
+    match this {
+        Value::Iterator(iter) => Ok(iter.clone()),
+        other => raise!(
+            TypeError,
+            "{fname}: receiver is not an iterator (got {other})"
+        ),
+    }
+}
+
+fn expect_n_args(args: &[Value], n: usize, fname: &str) -> Result<()> {
+    if args.len() != n {
+        raise!(
+            ArityError,
+            "{fname}: expected {n} argument(s), got {}",
+            args.len()
+        );
+    }
+    Ok(())
+}
+
+fn expect_count(args: &[Value], idx: usize, fname: &str) -> Result<usize> {
+    match args.get(idx) {
+        Some(Value::Int32(n)) if *n >= 0 => Ok(*n as usize),
+        Some(Value::Int32(n)) => raise!(ValueError, "{fname}: count must be non-negative, got {n}"),
+        Some(other) => raise!(TypeError, "{fname}: count must be Int32, got {other}"),
+        None => raise!(
+            ArityError,
+            "{fname}: missing count argument at position {idx}"
+        ),
+    }
+}
+
+fn expect_int(args: &[Value], idx: usize, fname: &str) -> Result<i32> {
+    match args.get(idx) {
+        Some(Value::Int32(n)) => Ok(*n),
+        Some(other) => raise!(
+            TypeError,
+            "{fname}: argument {idx} must be Int32, got {other}"
+        ),
+        None => raise!(ArityError, "{fname}: missing argument at position {idx}"),
+    }
+}
+
+/// Advances a shared iterator, refusing re-entrant use such as a generator
+/// that pulls from itself. Every element is a step of `interpreter`'s budget,
+/// so limits and cancellation reach loops that never evaluate an expression.
+fn advance(iter: &SharedIterator, interpreter: &Interpreter, fname: &str) -> Result<Option<Value>> {
+    interpreter.budget.tick()?;
+    let mut iter = iter
+        .try_borrow_mut()
+        .map_err(|_| sludge_error!(RuntimeError, "{fname}: iterator is already running"))?;
+    iter.next(interpreter)
+}
+
+/// Pulls every remaining element, checking the size limit as the list grows.
+pub(crate) fn drain(
+    iter: &SharedIterator,
+    interpreter: &Interpreter,
+    fname: &str,
+) -> Result<Vec<Value>> {
+    let mut out = Vec::new();
+    while let Some(v) = advance(iter, interpreter, fname)? {
+        out.push(v);
+        interpreter.budget.check_len(out.len())?;
+    }
+    Ok(out)
+}
+
+/// Turns an iterator or collection into a shared iterator.
+pub(crate) fn to_iterator(value: &Value, fname: &str) -> Result<SharedIterator> {
+    match value {
+        Value::Iterator(iter) => Ok(iter.clone()),
+        Value::List { values } => Ok(Rc::new(RefCell::new(ListIter {
+            values: values.clone(),
+            index: 0,
+        }))),
+        Value::Tuple { values } => Ok(Rc::new(RefCell::new(VecIter {
+            values: values.clone().into_iter(),
+        }))),
+        Value::Set { values } => Ok(Rc::new(RefCell::new(VecIter {
+            values: values
+                .borrow()
+                .iter()
+                .map(|h| h.as_value())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }))),
+        Value::Dictionary { values } => Ok(Rc::new(RefCell::new(VecIter {
+            values: values
+                .borrow()
+                .keys()
+                .map(|h| h.as_value())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }))),
+        other => raise!(TypeError, "{fname}: value is not iterable (got {other})"),
+    }
+}
+
+/// Walks a list by index, so it observes pushes made while iterating.
+#[derive(Debug)]
+struct ListIter {
+    values: Rc<RefCell<Vec<Value>>>,
+    index: usize,
+}
+
+impl ValueIterator for ListIter {
+    fn next(&mut self, _interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        let item = self.values.borrow().get(self.index).cloned();
+        if item.is_some() {
+            self.index += 1;
+        }
+        Ok(item)
+    }
+}
+
+#[derive(Debug)]
+struct VecIter {
+    values: std::vec::IntoIter<Value>,
+}
+
+impl ValueIterator for VecIter {
+    fn next(&mut self, _interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        Ok(self.values.next())
+    }
+}
+
+#[derive(Debug)]
+struct RangeIter {
+    next: i64,
+    end: i64,
+    step: i64,
+}
+
+impl ValueIterator for RangeIter {
+    fn next(&mut self, _interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        let more = if self.step > 0 {
+            self.next < self.end
+        } else {
+            self.next > self.end
+        };
+        if !more {
+            return Ok(None);
+        }
+        let current = self.next;
+        self.next += self.step;
+        Ok(Some(Value::Int32(current as i32)))
+    }
+}
+
+#[derive(Debug)]
+struct MapIter {
+    source: SharedIterator,
+    f: Value,
+    interpreter: Rc<Interpreter>,
+    index: usize,
+}
+
+impl ValueIterator for MapIter {
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        let Some(v) = advance(&self.source, interpreter, "map")? else {
+            return Ok(None);
+        };
+        let index = Value::Int32(self.index as i32);
+        self.index += 1;
+        call_callback(&self.interpreter, &self.f, &v, index, "map").map(Some)
+    }
+}
+
+#[derive(Debug)]
+struct FilterIter {
+    source: SharedIterator,
+    f: Value,
+    interpreter: Rc<Interpreter>,
+    index: usize,
+}
+
+impl ValueIterator for FilterIter {
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        while let Some(v) = advance(&self.source, interpreter, "filter")? {
+            let index = Value::Int32(self.index as i32);
+            self.index += 1;
+            if call_callback(&self.interpreter, &self.f, &v, index, "filter")?.to_bool()? {
+                return Ok(Some(v));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug)]
+struct TakeIter {
+    source: SharedIterator,
+    remaining: usize,
+}
+
+impl ValueIterator for TakeIter {
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        advance(&self.source, interpreter, "take")
+    }
+}
+
+#[derive(Debug)]
+struct SkipIter {
+    source: SharedIterator,
+    pending: usize,
+}
+
+impl ValueIterator for SkipIter {
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        while self.pending > 0 {
+            self.pending -= 1;
+            if advance(&self.source, interpreter, "skip")?.is_none() {
+                return Ok(None);
+            }
+        }
+        advance(&self.source, interpreter, "skip")
+    }
+}
+
+#[derive(Debug)]
+struct TakeWhileIter {
+    source: SharedIterator,
+    f: Value,
+    interpreter: Rc<Interpreter>,
+    done: bool,
+}
+
+impl ValueIterator for TakeWhileIter {
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+        if let Some(v) = advance(&self.source, interpreter, "take_while")? {
+            let keep = call_function(
+                &self.interpreter,
+                &self.f,
+                std::slice::from_ref(&v),
+                "take_while",
+            )?;
+            if keep.to_bool()? {
+                return Ok(Some(v));
+            }
+        }
+        self.done = true;
+        Ok(None)
+    }
+}
+
+#[derive(Debug)]
+struct ChainIter {
+    first: SharedIterator,
+    second: SharedIterator,
+}
+
+impl ValueIterator for ChainIter {
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        match advance(&self.first, interpreter, "chain")? {
+            Some(v) => Ok(Some(v)),
+            None => advance(&self.second, interpreter, "chain"),
+        }
+    }
+}
+
+pub fn range(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    if args.is_empty() || args.len() > 3 {
+        raise!(
+            ArityError,
+            "range: expected 1 to 3 argument(s), got {}",
+            args.len()
+        );
+    }
+    // `range(end)`, `range(start, end)` or `range(start, end, step)`; a
+    // null `end` or `step`, as left by `range(10, step: 2)`, is omitted.
+    let first = expect_int(args, 0, "range")?;
+    let (start, end) = match args.get(1) {
+        None | Some(Value::Null) => (0, first),
+        Some(_) => (first, expect_int(args, 1, "range")?),
+    };
+    let step = match args.get(2) {
+        None | Some(Value::Null) => 1,
+        Some(_) => expect_int(args, 2, "range")?,
+    };
+    if step == 0 {
+        raise!(ValueError, "range: step must not be zero");
+    }
+    Ok(Value::Iterator(Rc::new(RefCell::new(RangeIter {
+        next: start as i64,
+        end: end as i64,
+        step: step as i64,
+    }))))
+}
+
+pub fn iter(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Iterator(to_iterator(this, "iter")?))
+}
+
+pub fn next(interpreter: Rc<Interpreter>, this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let iter = expect_iterator(this, "next")?;
+    Ok(advance(&iter, &interpreter, "next")?.unwrap_or(Value::Null))
+}
+
+pub fn collect(
+    interpreter: Rc<Interpreter>,
+    this: &Value,
+    _args: &[Value],
+) -> Result<Value, Error> {
+    let iter = expect_iterator(this, "collect")?;
+    let out = drain(&iter, &interpreter, "collect")?;
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn take(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "take")?;
+    Ok(Value::Iterator(Rc::new(RefCell::new(TakeIter {
+        source: expect_iterator(this, "take")?,
+        remaining: expect_count(args, 0, "take")?,
+    }))))
+}
+
+pub fn skip(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "skip")?;
+    Ok(Value::Iterator(Rc::new(RefCell::new(SkipIter {
+        source: expect_iterator(this, "skip")?,
+        pending: expect_count(args, 0, "skip")?,
+    }))))
+}
+
+pub fn chain(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "chain")?;
+    Ok(Value::Iterator(Rc::new(RefCell::new(ChainIter {
+        first: expect_iterator(this, "chain")?,
+        second: to_iterator(&args[0], "chain")?,
+    }))))
+}
+
+pub fn map(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "map")?;
+    Ok(Value::Iterator(Rc::new(RefCell::new(MapIter {
+        source: expect_iterator(this, "map")?,
+        f: expect_callable(args, 0, "map")?.clone(),
+        interpreter,
+        index: 0,
+    }))))
+}
+
+pub fn filter(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "filter")?;
+    Ok(Value::Iterator(Rc::new(RefCell::new(FilterIter {
+        source: expect_iterator(this, "filter")?,
+        f: expect_callable(args, 0, "filter")?.clone(),
+        interpreter,
+        index: 0,
+    }))))
+}
+
+pub fn take_while(
+    interpreter: Rc<Interpreter>,
+    this: &Value,
+    args: &[Value],
+) -> Result<Value, Error> {
+    expect_n_args(args, 1, "take_while")?;
+    Ok(Value::Iterator(Rc::new(RefCell::new(TakeWhileIter {
+        source: expect_iterator(this, "take_while")?,
+        f: expect_callable(args, 0, "take_while")?.clone(),
+        interpreter,
+        done: false,
+    }))))
+}
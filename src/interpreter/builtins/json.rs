@@ -0,0 +1,141 @@
+use crate::interpreter::builtins::builtin;
+use crate::interpreter::error::{raise, sludge_error};
+use crate::interpreter::value::Value;
+use crate::interpreter::variable_scope::VariableScope;
+
+use anyhow::Error;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Declares the `json` module in `scope`.
+pub fn register(scope: &VariableScope) {
+    scope.declare("json".to_string(), module());
+}
+
+/// Builds the `json` module value holding `parse` and `stringify`.
+pub fn module() -> Value {
+    Value::Module {
+        name: "json",
+        members: Rc::new(HashMap::from([
+            (
+                "parse",
+                builtin("json.parse", Some(&["text", "floats?"]), parse),
+            ),
+            (
+                "stringify",
+                builtin("json.stringify", Some(&["value", "pretty?"]), stringify),
+            ),
+        ])),
+    }
+}
+
+/// How `json.parse` reads numbers with a fractional part, which have no
+/// Sludge value of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Floats {
+    /// Raise a `ValueError`, the default.
+    Error,
+    /// Drop the fractional part, so `1.5` reads as `1`.
+    Truncate,
+    /// Keep the number as written, as a string such as `"1.5"`.
+    String,
+}
+
+/// `json.parse(text, floats?)`: reads a JSON document. There is no float
+/// type, so numbers become ints: one with no fractional part such as `2.0`
+/// is read as an int, and any other raises a `ValueError` unless `floats`
+/// is `"truncate"` or `"string"`.
+pub fn parse(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let text = match args.first() {
+        Some(Value::String(s)) => s,
+        Some(other) => raise!(
+            TypeError,
+            "json.parse: argument must be a string (got {other})"
+        ),
+        None => raise!(ArityError, "json.parse: expected 1 or 2 argument(s), got 0"),
+    };
+    let floats = match args.get(1) {
+        None | Some(Value::Null) => Floats::Error,
+        Some(Value::String(mode)) if mode == "error" => Floats::Error,
+        Some(Value::String(mode)) if mode == "truncate" => Floats::Truncate,
+        Some(Value::String(mode)) if mode == "string" => Floats::String,
+        Some(other) => raise!(
+            ValueError,
+            "json.parse: floats must be \"error\", \"truncate\" or \"string\" (got {other:#})"
+        ),
+    };
+    let json: serde_json::Value = serde_json::from_str(text).map_err(|e| {
+        sludge_error!(
+            ValueError,
+            "json.parse: invalid JSON at line {} column {}: {}",
+            e.line(),
+            e.column(),
+            strip_position(&e)
+        )
+    })?;
+    let json = convert_floats(json, floats);
+    Value::from_json(json).map_err(|e| sludge_error!(ValueError, "json.parse: {e}"))
+}
+
+/// Rewrites the numbers with a fractional part as `floats` asks. Others,
+/// and every number under `Floats::Error`, are left for `Value::from_json`.
+fn convert_floats(json: serde_json::Value, floats: Floats) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match json {
+        Json::Number(n) if floats != Floats::Error => match n.as_f64() {
+            Some(f) if !n.is_i64() && !n.is_u64() && f.fract() != 0.0 => match floats {
+                Floats::Truncate => Json::from(f.trunc()),
+                _ => Json::String(n.to_string()),
+            },
+            _ => Json::Number(n),
+        },
+        Json::Array(items) => Json::Array(
+            items
+                .into_iter()
+                .map(|item| convert_floats(item, floats))
+                .collect(),
+        ),
+        Json::Object(entries) => Json::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k, convert_floats(v, floats)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+pub fn stringify(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let (value, pretty) = match args {
+        [value] => (value, false),
+        [value, Value::Boolean(pretty)] => (value, *pretty),
+        [_, other] => raise!(
+            TypeError,
+            "json.stringify: pretty flag must be a boolean (got {other})"
+        ),
+        _ => raise!(
+            ArityError,
+            "json.stringify: expected 1 or 2 argument(s), got {}",
+            args.len()
+        ),
+    };
+    let json = value
+        .to_json()
+        .map_err(|e| sludge_error!(TypeError, "json.stringify: {e}"))?;
+    let text = if pretty {
+        serde_json::to_string_pretty(&json)?
+    } else {
+        serde_json::to_string(&json)?
+    };
+    Ok(Value::String(text))
+}
+
+/// serde_json appends " at line L column C" to its messages; the position is
+/// reported separately, so drop it from the description.
+fn strip_position(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    match message.rfind(" at line ") {
+        Some(idx) => message[..idx].to_string(),
+        None => message,
+    }
+}
@@ -1,22 +1,54 @@
-use crate::ast::Expr;
 use crate::interpreter::Interpreter;
+use crate::interpreter::builtins::{call_callback, call_function, expect_callable};
+use crate::interpreter::error::{raise, sludge_error};
 use crate::interpreter::value::Value;
-use crate::interpreter::variable_scope::VariableScope;
 
-use anyhow::{Context, Error, Result, anyhow, bail};
+use anyhow::{Error, Result};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::rc::Rc;
 
 fn expect_list(this: &Value, fname: &str) -> Result<Rc<RefCell<Vec<Value>>>> {
     match this {
         Value::List { values } => Ok(values.clone()),
-        other => bail!("{}: receiver is not a list (got {})", fname, other),
+        other => raise!(
+            TypeError,
+            "{}: receiver is not a list (got {})",
+            fname,
+            other
+        ),
+    }
+}
+
+fn expect_list_arg(arg: &Value, fname: &str) -> Result<Rc<RefCell<Vec<Value>>>> {
+    match arg {
+        Value::List { values } => Ok(values.clone()),
+        other => raise!(
+            TypeError,
+            "{}: argument must be a list (got {})",
+            fname,
+            other
+        ),
+    }
+}
+
+fn expect_n_args(args: &[Value], n: usize, fname: &str) -> Result<()> {
+    if args.len() != n {
+        raise!(
+            ArityError,
+            "{}: expected {} argument(s), got {}",
+            fname,
+            n,
+            args.len()
+        );
     }
+    Ok(())
 }
 
 fn expect_n_args_at_least(args: &[Value], n: usize, fname: &str) -> Result<()> {
     if args.len() < n {
-        bail!(
+        raise!(
+            ArityError,
             "{}: expected at least {} argument(s), got {}",
             fname,
             n,
@@ -27,41 +59,45 @@ fn expect_n_args_at_least(args: &[Value], n: usize, fname: &str) -> Result<()> {
 }
 
 fn expect_index(args: &[Value], idx: usize, fname: &str) -> Result<usize> {
-    let v = args
-        .get(idx)
-        .ok_or_else(|| anyhow!("{}: missing index argument at position {}", fname, idx))?;
+    let v = args.get(idx).ok_or_else(|| {
+        sludge_error!(
+            ArityError,
+            "{}: missing index argument at position {}",
+            fname,
+            idx
+        )
+    })?;
     match v {
         Value::Int32(i) if *i >= 0 => Ok(*i as usize),
-        Value::Int32(i) => bail!("{}: index must be non-negative, got {}", fname, i),
-        other => bail!("{}: index must be Int32, got {}", fname, other),
-    }
-}
-
-fn expect_callable<'a>(
-    args: &'a [Value],
-    fname: &str,
-) -> Result<(&'a Vec<String>, &'a Expr, &'a Rc<VariableScope>)> {
-    // matches your Value::Function shape
-    match args.first() {
-        Some(Value::Function {
-            arguments,
-            statement,
-            scope,
-        }) => Ok((arguments, statement, scope)),
-        Some(other) => bail!(
-            "{}: first argument must be a function, got {}",
+        Value::Int32(i) => raise!(
+            ValueError,
+            "{}: index must be non-negative, got {}",
             fname,
-            other
+            i
         ),
-        None => bail!("{}: missing function argument", fname),
+        other => raise!(TypeError, "{}: index must be Int32, got {}", fname, other),
     }
 }
 
-fn expect_return(from: Value, fname: &str) -> Result<Value> {
-    match from {
-        Value::Return { value } => Ok(*value),
-        other => bail!("{fname}: function must `return` a value (got {other})"),
+fn compare(a: &Value, b: &Value, fname: &str) -> Result<Ordering> {
+    a.partial_cmp(b)
+        .ok_or_else(|| sludge_error!(TypeError, "{}: cannot compare {} with {}", fname, a, b))
+}
+
+fn extremum(this: &Value, wanted: Ordering, fname: &str) -> Result<Value> {
+    let values = expect_list(this, fname)?;
+    let values = values.borrow();
+    let mut iter = values.iter();
+    let mut best = match iter.next() {
+        Some(v) => v,
+        None => return Ok(Value::Null),
+    };
+    for v in iter {
+        if compare(v, best, fname)? == wanted {
+            best = v;
+        }
     }
+    Ok(best.clone())
 }
 
 pub fn new(_this: &Value, args: &[Value]) -> Result<Value, Error> {
@@ -93,7 +129,7 @@ pub fn pop(this: &Value, _args: &[Value]) -> Result<Value, Error> {
     let mut borrow = values.borrow_mut();
     match borrow.pop() {
         Some(v) => Ok(v),
-        None => bail!("pop: cannot pop from an empty list"),
+        None => raise!(IndexError, "pop: cannot pop from an empty list"),
     }
 }
 
@@ -109,7 +145,8 @@ pub fn at(this: &Value, args: &[Value]) -> Result<Value, Error> {
     let values = expect_list(this, "at")?;
     match values.borrow().get(idx) {
         Some(v) => Ok(v.clone()),
-        None => bail!(
+        None => raise!(
+            IndexError,
             "at: index {} out of bounds (len = {})",
             idx,
             values.borrow().len()
@@ -122,50 +159,28 @@ pub fn sum(this: &Value, _args: &[Value]) -> Result<Value, Error> {
     Ok(values.borrow().iter().sum())
 }
 pub fn map(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
-    let (fn_args, stmt, scope) = expect_callable(args, "map")?;
-    let param = fn_args
-        .first()
-        .ok_or_else(|| anyhow!("map: function must accept at least 1 parameter"))?;
-    let values = expect_list(this, "map")?;
+    let f = expect_callable(args, 0, "map")?;
+    let values = expect_list(this, "map")?.borrow().clone();
 
-    let out: Result<Vec<Value>> = values
-        .borrow()
+    let out = values
         .iter()
-        .map(|v| {
-            let child = Interpreter::new(VariableScope::branch(scope), interpreter.stdout.clone());
-            child.variables.declare(param.clone(), v.clone());
-
-            let evaluated = child
-                .eval_expr(stmt)
-                .with_context(|| "map: function evaluation failed")?;
-            let ret = expect_return(evaluated, "map")?;
-            Ok(ret)
-        })
-        .collect();
+        .enumerate()
+        .map(|(i, v)| call_callback(&interpreter, f, v, Value::Int32(i as i32), "map"))
+        .collect::<Result<Vec<_>>>()?;
 
     Ok(Value::List {
-        values: Rc::new(RefCell::new(out?)),
+        values: Rc::new(RefCell::new(out)),
     })
 }
 
 pub fn filter(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
-    let (fn_args, stmt, scope) = expect_callable(args, "filter")?;
-    let param = fn_args
-        .first()
-        .ok_or_else(|| anyhow!("filter: function must accept at least 1 parameter"))?;
-    let values = expect_list(this, "filter")?;
+    let f = expect_callable(args, 0, "filter")?;
+    let values = expect_list(this, "filter")?.borrow().clone();
 
     let mut out = Vec::new();
-    for v in values.borrow().iter() {
-        let child = Interpreter::new(VariableScope::branch(scope), interpreter.stdout.clone());
-        child.variables.declare(param.clone(), v.clone());
-
-        let evaluated = child
-            .eval_expr(stmt)
-            .with_context(|| "filter: function evaluation failed")?;
-        let ret = expect_return(evaluated, "filter")?;
-        if ret.to_bool()? {
-            out.push(v.clone());
+    for (i, v) in values.into_iter().enumerate() {
+        if call_callback(&interpreter, f, &v, Value::Int32(i as i32), "filter")?.to_bool()? {
+            out.push(v);
         }
     }
 
@@ -175,21 +190,11 @@ pub fn filter(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Res
 }
 
 pub fn all(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
-    let (fn_args, stmt, scope) = expect_callable(args, "all")?;
-    let param = fn_args
-        .first()
-        .ok_or_else(|| anyhow!("all: function must accept at least 1 parameter"))?;
-    let values = expect_list(this, "all")?;
+    let f = expect_callable(args, 0, "all")?;
+    let values = expect_list(this, "all")?.borrow().clone();
 
-    for v in values.borrow().iter() {
-        let child = Interpreter::new(VariableScope::branch(scope), interpreter.stdout.clone());
-        child.variables.declare(param.clone(), v.clone());
-
-        let evaluated = child
-            .eval_expr(stmt)
-            .with_context(|| "all: function evaluation failed")?;
-        let ret = expect_return(evaluated, "all")?;
-        if !ret.to_bool()? {
+    for (i, v) in values.iter().enumerate() {
+        if !call_callback(&interpreter, f, v, Value::Int32(i as i32), "all")?.to_bool()? {
             return Ok(Value::Boolean(false));
         }
     }
@@ -197,21 +202,11 @@ pub fn all(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result
 }
 
 pub fn any(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
-    let (fn_args, stmt, scope) = expect_callable(args, "any")?;
-    let param = fn_args
-        .first()
-        .ok_or_else(|| anyhow!("any: function must accept at least 1 parameter"))?;
-    let values = expect_list(this, "any")?;
+    let f = expect_callable(args, 0, "any")?;
+    let values = expect_list(this, "any")?.borrow().clone();
 
-    for v in values.borrow().iter() {
-        let child = Interpreter::new(VariableScope::branch(scope), interpreter.stdout.clone());
-        child.variables.declare(param.clone(), v.clone());
-
-        let evaluated = child
-            .eval_expr(stmt)
-            .with_context(|| "any: function evaluation failed")?;
-        let ret = expect_return(evaluated, "any")?;
-        if ret.to_bool()? {
+    for (i, v) in values.iter().enumerate() {
+        if call_callback(&interpreter, f, v, Value::Int32(i as i32), "any")?.to_bool()? {
             return Ok(Value::Boolean(true));
         }
     }
@@ -223,12 +218,285 @@ pub fn length(this: &Value, _args: &[Value]) -> Result<Value, Error> {
     Ok(Value::Int32(values.borrow().len() as i32))
 }
 
-// TODO:
-// insert
-// truncate
-// has
-// remove
-// reverse
-// sort
-// chunks
-// flatten
+pub fn insert(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 2, "insert")?;
+    let idx = expect_index(args, 0, "insert")?;
+    let values = expect_list(this, "insert")?;
+    let len = values.borrow().len();
+    if idx > len {
+        raise!(
+            IndexError,
+            "insert: index {} out of bounds (len = {})",
+            idx,
+            len
+        );
+    }
+    values.borrow_mut().insert(idx, args[1].clone());
+    Ok(Value::Int32(values.borrow().len() as i32))
+}
+
+pub fn truncate(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "truncate")?;
+    let len = expect_index(args, 0, "truncate")?;
+    let values = expect_list(this, "truncate")?;
+    values.borrow_mut().truncate(len);
+    Ok(Value::Null)
+}
+
+pub fn has(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "has")?;
+    let values = expect_list(this, "has")?;
+    Ok(Value::Boolean(values.borrow().contains(&args[0])))
+}
+
+pub fn remove(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "remove")?;
+    let idx = expect_index(args, 0, "remove")?;
+    let values = expect_list(this, "remove")?;
+    let len = values.borrow().len();
+    if idx >= len {
+        raise!(
+            IndexError,
+            "remove: index {} out of bounds (len = {})",
+            idx,
+            len
+        );
+    }
+    Ok(values.borrow_mut().remove(idx))
+}
+
+pub fn reverse(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let values = expect_list(this, "reverse")?;
+    values.borrow_mut().reverse();
+    Ok(Value::Null)
+}
+
+pub fn sort(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let values = expect_list(this, "sort")?;
+
+    // Sort a copy so a failed comparison leaves the receiver untouched.
+    let sorted = merge_sort(values.borrow().clone(), &mut |a, b| compare(a, b, "sort"))?;
+
+    *values.borrow_mut() = sorted;
+    Ok(Value::Null)
+}
+
+pub fn sort_by(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "sort_by")?;
+    let f = expect_callable(args, 0, "sort_by")?;
+    let values = expect_list(this, "sort_by")?;
+
+    let sorted = merge_sort(values.borrow().clone(), &mut |a, b| match call_function(
+        &interpreter,
+        f,
+        &[a.clone(), b.clone()],
+        "sort_by",
+    )? {
+        Value::Int32(n) => Ok(n.cmp(&0)),
+        other => raise!(
+            TypeError,
+            "sort_by: comparator must return an int (got {other})"
+        ),
+    })?;
+
+    *values.borrow_mut() = sorted;
+    Ok(Value::Null)
+}
+
+/// A stable merge sort that stops at the first failed comparison. Unlike
+/// `slice::sort_by`, it does not need `cmp` to be a total order: a script
+/// comparator that is inconsistent gives a badly sorted list, not a panic.
+fn merge_sort(
+    mut values: Vec<Value>,
+    cmp: &mut impl FnMut(&Value, &Value) -> Result<Ordering>,
+) -> Result<Vec<Value>> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+    let right = values.split_off(values.len() / 2);
+    let mut left = merge_sort(values, cmp)?.into_iter().peekable();
+    let mut right = merge_sort(right, cmp)?.into_iter().peekable();
+
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Ties keep the left element first, so the sort is stable.
+        let next = if cmp(b, a)? == Ordering::Less {
+            right.next()
+        } else {
+            left.next()
+        };
+        out.extend(next);
+    }
+    out.extend(left);
+    out.extend(right);
+    Ok(out)
+}
+
+pub fn chunks(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "chunks")?;
+    let size = expect_index(args, 0, "chunks")?;
+    if size == 0 {
+        raise!(ValueError, "chunks: chunk size must be greater than 0");
+    }
+    let values = expect_list(this, "chunks")?;
+    let out = values
+        .borrow()
+        .chunks(size)
+        .map(|chunk| Value::List {
+            values: Rc::new(RefCell::new(chunk.to_vec())),
+        })
+        .collect::<Vec<_>>();
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn flatten(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let values = expect_list(this, "flatten")?;
+    let mut out = Vec::new();
+    for v in values.borrow().iter() {
+        match v {
+            Value::List { values } => out.extend(values.borrow().iter().cloned()),
+            other => out.push(other.clone()),
+        }
+    }
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn reduce(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "reduce")?;
+    let f = expect_callable(args, 0, "reduce")?;
+    let values = expect_list(this, "reduce")?.borrow().clone();
+    let mut iter = values.into_iter();
+    let mut acc = iter
+        .next()
+        .ok_or_else(|| sludge_error!(IndexError, "reduce: cannot reduce an empty list"))?;
+    for v in iter {
+        acc = call_function(&interpreter, f, &[acc, v], "reduce")?;
+    }
+    Ok(acc)
+}
+
+pub fn fold(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 2, "fold")?;
+    let f = expect_callable(args, 1, "fold")?;
+    let values = expect_list(this, "fold")?.borrow().clone();
+    let mut acc = args[0].clone();
+    for v in values {
+        acc = call_function(&interpreter, f, &[acc, v], "fold")?;
+    }
+    Ok(acc)
+}
+
+pub fn zip(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "zip")?;
+    let a = expect_list(this, "zip")?;
+    let b = expect_list_arg(&args[0], "zip")?;
+    let out = a
+        .borrow()
+        .iter()
+        .zip(b.borrow().iter())
+        .map(|(x, y)| Value::Tuple {
+            values: vec![x.clone(), y.clone()],
+        })
+        .collect::<Vec<_>>();
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn enumerate(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let values = expect_list(this, "enumerate")?;
+    let out = values
+        .borrow()
+        .iter()
+        .enumerate()
+        .map(|(i, v)| Value::Tuple {
+            values: vec![Value::Int32(i as i32), v.clone()],
+        })
+        .collect::<Vec<_>>();
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn find(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "find")?;
+    let f = expect_callable(args, 0, "find")?;
+    let values = expect_list(this, "find")?.borrow().clone();
+    for (i, v) in values.into_iter().enumerate() {
+        if call_callback(&interpreter, f, &v, Value::Int32(i as i32), "find")?.to_bool()? {
+            return Ok(v);
+        }
+    }
+    Ok(Value::Null)
+}
+
+pub fn index_of(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "index_of")?;
+    let values = expect_list(this, "index_of")?;
+    match values.borrow().iter().position(|v| *v == args[0]) {
+        Some(i) => Ok(Value::Int32(i as i32)),
+        None => Ok(Value::Null),
+    }
+}
+
+pub fn slice(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args_at_least(args, 1, "slice")?;
+    let values = expect_list(this, "slice")?;
+    let len = values.borrow().len();
+    let start = expect_index(args, 0, "slice")?.min(len);
+    let end = match args.get(1) {
+        Some(_) => expect_index(args, 1, "slice")?.min(len),
+        None => len,
+    };
+    let out = if start < end {
+        values.borrow()[start..end].to_vec()
+    } else {
+        Vec::new()
+    };
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn concat(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let values = expect_list(this, "concat")?;
+    let mut out = values.borrow().clone();
+    for arg in args {
+        out.extend(expect_list_arg(arg, "concat")?.borrow().iter().cloned());
+    }
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn unique(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let values = expect_list(this, "unique")?;
+    let mut out: Vec<Value> = Vec::new();
+    for v in values.borrow().iter() {
+        if !out.contains(v) {
+            out.push(v.clone());
+        }
+    }
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn min(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    extremum(this, Ordering::Less, "min")
+}
+
+pub fn max(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    extremum(this, Ordering::Greater, "max")
+}
+
+pub fn count(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "count")?;
+    let values = expect_list(this, "count")?;
+    let n = values.borrow().iter().filter(|v| **v == args[0]).count();
+    Ok(Value::Int32(n as i32))
+}
@@ -1,3 +1,113 @@
 pub mod dict;
+pub mod fs;
+pub mod functional;
+pub mod iterator;
+pub mod json;
 pub mod list;
+pub mod process;
 pub mod set;
+pub mod sys;
+
+use crate::interpreter::Interpreter;
+use crate::interpreter::error::raise;
+use crate::interpreter::signature::{Signature, SignedBuiltin};
+use crate::interpreter::value::{NamedBuiltin, Value};
+use crate::interpreter::variable_scope::VariableScope;
+
+use anyhow::{Error, Result};
+use std::rc::Rc;
+
+/// Declares the collection constructors `list`, `dict`, `from_items`,
+/// `set` and `range`, `error`, and the function combinators `compose` and
+/// `partial`.
+pub fn register_core(scope: &VariableScope) {
+    type CoreFn = fn(&Value, &[Value]) -> Result<Value, Error>;
+    let builtins: [(&'static str, Option<&[&str]>, CoreFn); 8] = [
+        ("list", None, list::new),
+        ("dict", None, dict::dict),
+        ("from_items", None, dict::from_items),
+        ("set", None, set::set),
+        ("range", Some(&["start", "end?", "step?"]), iterator::range),
+        ("error", Some(&["message"]), error),
+        ("compose", Some(&["f", "g"]), functional::compose),
+        ("partial", Some(&["f", "...args"]), functional::partial),
+    ];
+    for (name, params, f) in builtins {
+        scope.declare(name.to_string(), builtin(name, params, f));
+    }
+}
+
+/// Wraps a builtin function, checking calls against its parameters when
+/// it declares them.
+pub(crate) fn builtin(
+    name: &'static str,
+    params: Option<&[&str]>,
+    f: fn(&Value, &[Value]) -> Result<Value, Error>,
+) -> Value {
+    match params {
+        Some(params) => Value::BuiltinFn(Rc::new(SignedBuiltin {
+            name: name.to_string(),
+            signature: Signature::new(params),
+            f: move |args: &[Value]| f(&Value::Null, args),
+        })),
+        None => Value::BuiltinFn(Rc::new(NamedBuiltin {
+            name,
+            this: Value::Null,
+            f,
+        })),
+    }
+}
+
+pub(crate) fn expect_callable<'a>(args: &'a [Value], idx: usize, fname: &str) -> Result<&'a Value> {
+    match args.get(idx) {
+        Some(f @ (Value::Function { .. } | Value::BuiltinFn(_))) => Ok(f),
+        Some(other) => raise!(
+            TypeError,
+            "{}: argument must be a function, got {}",
+            fname,
+            other
+        ),
+        None => raise!(ArityError, "{}: missing function argument", fname),
+    }
+}
+
+pub(crate) fn call_function(
+    interpreter: &Interpreter,
+    callable: &Value,
+    args: &[Value],
+    fname: &str,
+) -> Result<Value> {
+    interpreter.call_value(callable, args).map_err(|e| {
+        let message = format!("{fname}: {e}");
+        e.context(message)
+    })
+}
+
+/// Invokes a per-element callback, passing `extra` (an index or key) as a
+/// second argument when a user function declares two or more parameters.
+pub(crate) fn call_callback(
+    interpreter: &Interpreter,
+    callable: &Value,
+    value: &Value,
+    extra: Value,
+    fname: &str,
+) -> Result<Value> {
+    match callable {
+        Value::Function { signature, .. } if signature.params.len() >= 2 => {
+            call_function(interpreter, callable, &[value.clone(), extra], fname)
+        }
+        _ => call_function(interpreter, callable, std::slice::from_ref(value), fname),
+    }
+}
+
+/// Raises a `UserError` with the given message, like `throw`.
+pub fn error(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    match args {
+        [message] => raise!(UserError, "{message}"),
+        _ => raise!(
+            ArityError,
+            "error: expected 1 argument(s), got {}",
+            args.len()
+        ),
+    }
+}
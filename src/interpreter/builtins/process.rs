@@ -0,0 +1,260 @@
+use crate::interpreter::error::raise;
+use crate::interpreter::value::{BuiltinFn, Hashable, NativeObject, Value};
+use crate::interpreter::variable_scope::VariableScope;
+
+use anyhow::{Context, Error, Result};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::thread::JoinHandle;
+
+type ProcessFn = fn(&[Value]) -> Result<Value, Error>;
+
+/// A subprocess builtin that only runs when the host passed `--allow-run`.
+pub struct ProcessBuiltin {
+    pub name: &'static str,
+    pub allow_run: bool,
+    pub f: ProcessFn,
+}
+
+impl std::fmt::Debug for ProcessBuiltin {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_tuple("Builtin").field(&self.name).finish()
+    }
+}
+
+impl BuiltinFn for ProcessBuiltin {
+    fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        if !self.allow_run {
+            raise!(
+                PermissionError,
+                "{}: permission denied: running subprocesses is not granted (use --allow-run)",
+                self.name
+            );
+        }
+        (self.f)(args)
+    }
+}
+
+/// Declares the subprocess builtins in `scope`.
+pub fn register(scope: &VariableScope, allow_run: bool) {
+    let builtins: [(&'static str, ProcessFn); 2] = [("exec", exec), ("spawn", spawn)];
+    for (name, f) in builtins {
+        scope.declare(
+            name.to_string(),
+            Value::BuiltinFn(Rc::new(ProcessBuiltin { name, allow_run, f })),
+        );
+    }
+}
+
+fn expect_string<'a>(v: &'a Value, what: &str, fname: &str) -> Result<&'a str> {
+    match v {
+        Value::String(s) => Ok(s),
+        other => raise!(TypeError, "{fname}: {what} must be a string (got {other})"),
+    }
+}
+
+/// Builds a command from `(cmd, args?, options?)`. Supported options are
+/// `stdin` (string piped to the process), `cwd` and `env` (dict of overrides).
+fn build_command(args: &[Value], fname: &str) -> Result<(Command, Option<String>)> {
+    if args.is_empty() || args.len() > 3 {
+        raise!(
+            ArityError,
+            "{fname}: expected 1 to 3 argument(s), got {}",
+            args.len()
+        );
+    }
+
+    let mut command = Command::new(expect_string(&args[0], "command", fname)?);
+
+    match args.get(1) {
+        None | Some(Value::Null) => {}
+        Some(Value::List { values }) => {
+            for arg in values.borrow().iter() {
+                command.arg(expect_string(arg, "argument", fname)?);
+            }
+        }
+        Some(other) => raise!(TypeError, "{fname}: arguments must be a list (got {other})"),
+    }
+
+    let mut stdin = None;
+    match args.get(2) {
+        None | Some(Value::Null) => {}
+        Some(Value::Dictionary { values }) => {
+            for (key, value) in values.borrow().iter() {
+                match (key, value) {
+                    (Hashable::String(k), v) if k == "stdin" => {
+                        stdin = Some(expect_string(v, "stdin", fname)?.to_string());
+                    }
+                    (Hashable::String(k), v) if k == "cwd" => {
+                        command.current_dir(expect_string(v, "cwd", fname)?);
+                    }
+                    (Hashable::String(k), Value::Dictionary { values }) if k == "env" => {
+                        for (name, value) in values.borrow().iter() {
+                            let value = expect_string(value, "env value", fname)?;
+                            command.env(name.to_string(), value);
+                        }
+                    }
+                    (Hashable::String(k), other) if k == "env" => {
+                        raise!(TypeError, "{fname}: env must be a dict (got {other})")
+                    }
+                    (other, _) => raise!(ValueError, "{fname}: unknown option '{other}'"),
+                }
+            }
+        }
+        Some(other) => raise!(TypeError, "{fname}: options must be a dict (got {other})"),
+    }
+
+    Ok((command, stdin))
+}
+
+fn record(status: Option<i32>, stdout: Value, stderr: Value) -> Value {
+    let status = status.map(Value::Int32).unwrap_or(Value::Null);
+    Value::Dictionary {
+        values: Rc::new(RefCell::new(HashMap::from([
+            (Hashable::String("status".to_string()), status),
+            (Hashable::String("stdout".to_string()), stdout),
+            (Hashable::String("stderr".to_string()), stderr),
+        ]))),
+    }
+}
+
+/// Feeds `input` to the child's stdin from another thread, so a child that
+/// fills its output pipes before reading everything is not deadlocked.
+fn write_stdin(child: &mut Child, input: Option<String>) -> Option<StdinWriter> {
+    let mut pipe = child.stdin.take()?;
+    let input = input?;
+    Some(std::thread::spawn(move || pipe.write_all(input.as_bytes())))
+}
+
+type StdinWriter = JoinHandle<std::io::Result<()>>;
+
+/// Waits for the stdin writer. A child that exits without reading all its
+/// input closes the pipe, which is not an error.
+fn finish_stdin(writer: Option<StdinWriter>, fname: &str, program: &str) -> Result<()> {
+    let Some(writer) = writer else {
+        return Ok(());
+    };
+    match writer.join() {
+        Ok(Err(e)) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+            Err(e).with_context(|| format!("{fname}: failed to write stdin of '{program}'"))
+        }
+        Ok(_) => Ok(()),
+        Err(_) => raise!(
+            RuntimeError,
+            "{fname}: stdin writer for '{program}' panicked"
+        ),
+    }
+}
+
+/// Runs a command to completion, capturing its output.
+pub fn exec(args: &[Value]) -> Result<Value, Error> {
+    let (mut command, stdin) = build_command(args, "exec")?;
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let mut child = command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("exec: failed to start '{program}'"))?;
+
+    let writer = write_stdin(&mut child, stdin);
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("exec: failed to wait for '{program}'"))?;
+    finish_stdin(writer, "exec", &program)?;
+
+    Ok(record(
+        output.status.code(),
+        Value::String(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Value::String(String::from_utf8_lossy(&output.stderr).into_owned()),
+    ))
+}
+
+/// Starts a command in the background with stdout and stderr attached to
+/// the terminal, so its output streams live. Returns a process handle; call
+/// its `wait()` to get the exit status.
+pub fn spawn(args: &[Value]) -> Result<Value, Error> {
+    let (mut command, stdin) = build_command(args, "spawn")?;
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let mut child = command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .spawn()
+        .with_context(|| format!("spawn: failed to start '{program}'"))?;
+
+    let writer = write_stdin(&mut child, stdin);
+    Ok(Value::Native(Rc::new(Process {
+        program,
+        child: RefCell::new(child),
+        writer: RefCell::new(writer),
+    })))
+}
+
+/// A process started by `spawn`. Its `pid` is a property; `wait()` blocks
+/// until it exits and returns the exit status (null if it was killed by a
+/// signal), and `kill()` stops it. A process that is never waited for keeps
+/// running after the script ends.
+#[derive(Debug)]
+struct Process {
+    program: String,
+    child: RefCell<Child>,
+    writer: RefCell<Option<StdinWriter>>,
+}
+
+impl NativeObject for Process {
+    fn type_name(&self) -> &str {
+        "process"
+    }
+
+    fn get_member(&self, field: &str) -> Option<Value> {
+        match field {
+            "pid" => Some(Value::Int32(self.child.borrow().id() as i32)),
+            _ => None,
+        }
+    }
+
+    fn call_method(&self, method: &str, args: &[Value]) -> Result<Value, Error> {
+        if !args.is_empty() {
+            raise!(
+                ArityError,
+                "{method}: expected 0 argument(s), got {}",
+                args.len()
+            );
+        }
+        match method {
+            "wait" => {
+                let status = self
+                    .child
+                    .borrow_mut()
+                    .wait()
+                    .with_context(|| format!("wait: failed to wait for '{}'", self.program))?;
+                finish_stdin(self.writer.take(), "wait", &self.program)?;
+                Ok(status.code().map(Value::Int32).unwrap_or(Value::Null))
+            }
+            "kill" => {
+                self.child
+                    .borrow_mut()
+                    .kill()
+                    .with_context(|| format!("kill: failed to kill '{}'", self.program))?;
+                Ok(Value::Null)
+            }
+            other => raise!(NameError, "unknown member '{other}' on type process"),
+        }
+    }
+
+    fn fmt_display(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<process {} {}>", self.child.borrow().id(), self.program)
+    }
+}
@@ -1,6 +1,9 @@
+use crate::interpreter::Interpreter;
+use crate::interpreter::builtins::{call_function, expect_callable};
+use crate::interpreter::error::raise;
 use crate::interpreter::value::{Hashable, Value};
 
-use anyhow::{Context, Error, Result, bail};
+use anyhow::{Context, Error, Result};
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::rc::Rc;
@@ -8,13 +11,17 @@ use std::rc::Rc;
 fn expect_set(this: &Value, fname: &str) -> Result<Rc<RefCell<HashSet<Hashable>>>> {
     match this {
         Value::Set { values } => Ok(values.clone()),
-        other => bail!("{fname}: receiver is not a set (got {other})"),
+        other => raise!(TypeError, "{fname}: receiver is not a set (got {other})"),
     }
 }
 
 fn expect_n_args(args: &[Value], n: usize, fname: &str) -> Result<()> {
     if args.len() != n {
-        bail!("{fname}: expected {n} argument(s), got {}", args.len());
+        raise!(
+            ArityError,
+            "{fname}: expected {n} argument(s), got {}",
+            args.len()
+        );
     }
     Ok(())
 }
@@ -24,6 +31,13 @@ fn expect_hashable(v: &Value, fname: &str) -> Result<Hashable> {
         .with_context(|| format!("{fname}: value is not hashable (got {v})"))
 }
 
+fn expect_set_arg(arg: &Value, fname: &str) -> Result<Rc<RefCell<HashSet<Hashable>>>> {
+    match arg {
+        Value::Set { values } => Ok(values.clone()),
+        other => raise!(TypeError, "{fname}: argument must be a set (got {other})"),
+    }
+}
+
 pub fn set(_this: &Value, args: &[Value]) -> Result<Value, Error> {
     let mut hs: HashSet<Hashable> = HashSet::with_capacity(args.len());
     for (i, v) in args.iter().enumerate() {
@@ -40,7 +54,7 @@ pub fn union(this: &Value, args: &[Value]) -> Result<Value, Error> {
     let a = expect_set(this, "union")?;
     let b = match &args[0] {
         Value::Set { values } => values.clone(),
-        other => bail!("union: argument must be a set (got {other})"),
+        other => raise!(TypeError, "union: argument must be a set (got {other})"),
     };
 
     let out: HashSet<Hashable> = a.borrow().union(&b.borrow()).cloned().collect();
@@ -54,7 +68,10 @@ pub fn intersection(this: &Value, args: &[Value]) -> Result<Value, Error> {
     let a = expect_set(this, "intersection")?;
     let b = match &args[0] {
         Value::Set { values } => values.clone(),
-        other => bail!("intersection: argument must be a set (got {other})"),
+        other => raise!(
+            TypeError,
+            "intersection: argument must be a set (got {other})"
+        ),
     };
 
     let out: HashSet<Hashable> = a.borrow().intersection(&b.borrow()).cloned().collect();
@@ -68,7 +85,10 @@ pub fn difference(this: &Value, args: &[Value]) -> Result<Value, Error> {
     let a = expect_set(this, "difference")?;
     let b = match &args[0] {
         Value::Set { values } => values.clone(),
-        other => bail!("difference: argument must be a set (got {other})"),
+        other => raise!(
+            TypeError,
+            "difference: argument must be a set (got {other})"
+        ),
     };
 
     let out: HashSet<Hashable> = a.borrow().difference(&b.borrow()).cloned().collect();
@@ -104,3 +124,78 @@ pub fn remove(this: &Value, args: &[Value]) -> Result<Value, Error> {
     let existed = s.borrow_mut().remove(&h);
     Ok(Value::Boolean(existed))
 }
+
+pub fn symmetric_difference(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "symmetric_difference")?;
+    let a = expect_set(this, "symmetric_difference")?;
+    let b = expect_set_arg(&args[0], "symmetric_difference")?;
+
+    let out: HashSet<Hashable> = a
+        .borrow()
+        .symmetric_difference(&b.borrow())
+        .cloned()
+        .collect();
+    Ok(Value::Set {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn is_subset(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "is_subset")?;
+    let a = expect_set(this, "is_subset")?;
+    let b = expect_set_arg(&args[0], "is_subset")?;
+    Ok(Value::Boolean(a.borrow().is_subset(&b.borrow())))
+}
+
+pub fn is_superset(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "is_superset")?;
+    let a = expect_set(this, "is_superset")?;
+    let b = expect_set_arg(&args[0], "is_superset")?;
+    Ok(Value::Boolean(a.borrow().is_superset(&b.borrow())))
+}
+
+pub fn is_disjoint(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "is_disjoint")?;
+    let a = expect_set(this, "is_disjoint")?;
+    let b = expect_set_arg(&args[0], "is_disjoint")?;
+    Ok(Value::Boolean(a.borrow().is_disjoint(&b.borrow())))
+}
+
+pub fn to_list(this: &Value, _args: &[Value]) -> Result<Value, Error> {
+    let s = expect_set(this, "to_list")?;
+    let list = s.borrow().iter().map(|h| h.as_value()).collect::<Vec<_>>();
+    Ok(Value::List {
+        values: Rc::new(RefCell::new(list)),
+    })
+}
+
+pub fn map(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "map")?;
+    let f = expect_callable(args, 0, "map")?;
+    let items = expect_set(this, "map")?.borrow().clone();
+
+    let mut out = HashSet::with_capacity(items.len());
+    for h in items {
+        let mapped = call_function(&interpreter, f, &[h.as_value()], "map")?;
+        out.insert(expect_hashable(&mapped, "map")?);
+    }
+    Ok(Value::Set {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
+
+pub fn filter(interpreter: Rc<Interpreter>, this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "filter")?;
+    let f = expect_callable(args, 0, "filter")?;
+    let items = expect_set(this, "filter")?.borrow().clone();
+
+    let mut out = HashSet::new();
+    for h in items {
+        if call_function(&interpreter, f, &[h.as_value()], "filter")?.to_bool()? {
+            out.insert(h);
+        }
+    }
+    Ok(Value::Set {
+        values: Rc::new(RefCell::new(out)),
+    })
+}
@@ -0,0 +1,146 @@
+use crate::interpreter::error::raise;
+use crate::interpreter::value::{NamedBuiltin, Value};
+use crate::interpreter::variable_scope::VariableScope;
+
+use anyhow::{Context, Error, Result};
+use std::cell::RefCell;
+use std::io::{BufRead, Read};
+use std::rc::Rc;
+
+/// Raised by `exit(code)` to unwind the running program. Hosts should
+/// downcast for it and terminate with `code` instead of reporting an error.
+#[derive(Debug)]
+pub struct Exit {
+    pub code: i32,
+}
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit({})", self.code)
+    }
+}
+
+impl std::error::Error for Exit {}
+
+impl Exit {
+    /// Finds an `exit(code)` request anywhere in an error's context chain.
+    pub fn find(err: &Error) -> Option<&Exit> {
+        err.chain().find_map(|cause| cause.downcast_ref::<Exit>())
+    }
+}
+
+/// Declares the process builtins in `scope`: `args` (answering `argv`),
+/// `env`, `read_line`, `read_stdin` and `exit`.
+pub fn register(scope: &VariableScope, argv: Vec<String>) {
+    type SysFn = fn(&Value, &[Value]) -> Result<Value, Error>;
+    scope.declare("args".to_string(), args_builtin(argv));
+    let builtins: [(&'static str, SysFn); 4] = [
+        ("env", env),
+        ("read_line", read_line),
+        ("read_stdin", read_stdin),
+        ("exit", exit),
+    ];
+    for (name, f) in builtins {
+        scope.declare(
+            name.to_string(),
+            Value::BuiltinFn(Rc::new(NamedBuiltin {
+                name,
+                this: Value::Null,
+                f,
+            })),
+        );
+    }
+}
+
+fn expect_n_args(args: &[Value], n: usize, fname: &str) -> Result<()> {
+    if args.len() != n {
+        raise!(
+            ArityError,
+            "{fname}: expected {n} argument(s), got {}",
+            args.len()
+        );
+    }
+    Ok(())
+}
+
+/// Builds the `args()` builtin; the command line arguments are carried as
+/// the receiver.
+pub fn args_builtin(argv: Vec<String>) -> Value {
+    let values = argv.into_iter().map(Value::String).collect();
+    Value::BuiltinFn(Rc::new(NamedBuiltin {
+        name: "args",
+        this: Value::List {
+            values: Rc::new(RefCell::new(values)),
+        },
+        f: args,
+    }))
+}
+
+pub fn args(this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 0, "args")?;
+    match this {
+        // Hand out a copy so scripts cannot mutate the process arguments.
+        Value::List { values } => Ok(Value::List {
+            values: Rc::new(RefCell::new(values.borrow().clone())),
+        }),
+        other => raise!(TypeError, "args: receiver is not a list (got {other})"),
+    }
+}
+
+pub fn env(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 1, "env")?;
+    let name = match &args[0] {
+        Value::String(s) => s,
+        other => raise!(
+            TypeError,
+            "env: variable name must be a string (got {other})"
+        ),
+    };
+    match std::env::var(name) {
+        Ok(value) => Ok(Value::String(value)),
+        Err(_) => Ok(Value::Null),
+    }
+}
+
+pub fn read_line(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 0, "read_line")?;
+    let mut line = String::new();
+    let read = std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("read_line: failed to read from stdin")?;
+    if read == 0 {
+        return Ok(Value::Null);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Value::String(line))
+}
+
+pub fn read_stdin(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    expect_n_args(args, 0, "read_stdin")?;
+    let mut contents = String::new();
+    std::io::stdin()
+        .lock()
+        .read_to_string(&mut contents)
+        .context("read_stdin: failed to read from stdin")?;
+    Ok(Value::String(contents))
+}
+
+pub fn exit(_this: &Value, args: &[Value]) -> Result<Value, Error> {
+    let code = match args {
+        [] => 0,
+        [Value::Int32(code)] => *code,
+        [other] => raise!(TypeError, "exit: status code must be Int32 (got {other})"),
+        _ => raise!(
+            ArityError,
+            "exit: expected at most 1 argument(s), got {}",
+            args.len()
+        ),
+    };
+    Err(Exit { code }.into())
+}
@@ -0,0 +1,291 @@
+use crate::interpreter::error::{SludgeError, raise};
+use crate::interpreter::value::{BuiltinFn, Hashable, NativeObject, Value};
+
+use anyhow::{Error, Result};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// Converts a Sludge value into a Rust type, for host function arguments.
+pub trait FromValue: Sized {
+    /// Converts `value`, failing with a `TypeError` such as
+    /// `expected int, got "x"` when it has the wrong type.
+    fn from_value(value: &Value) -> Result<Self>;
+}
+
+/// Converts a Rust type into a Sludge value, for host function results.
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for i32 {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Int32(i) => Ok(*i),
+            other => raise!(TypeError, "expected int, got {other:#}"),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            other => raise!(TypeError, "expected boolean, got {other:#}"),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            other => raise!(TypeError, "expected string, got {other:#}"),
+        }
+    }
+}
+
+/// `null` converts to `None`; anything else must convert to `T`.
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+/// Accepts lists and tuples.
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::List { values } => values.borrow().iter().map(T::from_value).collect(),
+            Value::Tuple { values } => values.iter().map(T::from_value).collect(),
+            other => raise!(TypeError, "expected list, got {other:#}"),
+        }
+    }
+}
+
+impl<K, V> FromValue for HashMap<K, V>
+where
+    K: FromValue + Eq + Hash,
+    V: FromValue,
+{
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Dictionary { values } => values
+                .borrow()
+                .iter()
+                .map(|(k, v)| Ok((K::from_value(&k.as_value())?, V::from_value(v)?)))
+                .collect(),
+            other => raise!(TypeError, "expected dict, got {other:#}"),
+        }
+    }
+}
+
+impl FromValue for Rc<dyn NativeObject> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Native(object) => Ok(object.clone()),
+            other => raise!(TypeError, "expected native object, got {other:#}"),
+        }
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self) -> Value {
+        Value::Int32(self)
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl IntoValue for Rc<dyn NativeObject> {
+    fn into_value(self) -> Value {
+        Value::Native(self)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map(IntoValue::into_value).unwrap_or(Value::Null)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::List {
+            values: Rc::new(RefCell::new(
+                self.into_iter().map(IntoValue::into_value).collect(),
+            )),
+        }
+    }
+}
+
+impl<V: IntoValue> IntoValue for HashMap<String, V> {
+    fn into_value(self) -> Value {
+        Value::Dictionary {
+            values: Rc::new(RefCell::new(
+                self.into_iter()
+                    .map(|(k, v)| (Hashable::String(k), v.into_value()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// The result of a host function: either a plain value or a `Result` whose
+/// error is raised in the script.
+pub trait IntoHostResult {
+    fn into_host_result(self) -> Result<Value>;
+}
+
+impl<T: IntoValue> IntoHostResult for T {
+    fn into_host_result(self) -> Result<Value> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue> IntoHostResult for Result<T> {
+    fn into_host_result(self) -> Result<Value> {
+        self.map(IntoValue::into_value)
+    }
+}
+
+/// A Rust function callable from scripts, with its arguments converted
+/// through `FromValue`. Implemented for closures of up to six arguments.
+pub trait HostFn<Args>: 'static {
+    const ARITY: usize;
+
+    /// Converts `args` and calls the function. `args` has already been
+    /// checked to hold `ARITY` values.
+    fn invoke(&self, name: &str, args: &[Value]) -> Result<Value>;
+}
+
+fn convert_arg<T: FromValue>(name: &str, args: &[Value], idx: usize) -> Result<T> {
+    T::from_value(&args[idx]).map_err(|e| with_prefix(e, &format!("{name}: argument {idx}")))
+}
+
+/// Prefixes the message of `err` with `prefix`. A typed error is rewritten
+/// in place, so a script's `catch` sees the prefix too, not only the host.
+fn with_prefix(mut err: Error, prefix: &str) -> Error {
+    if let Some(error) = SludgeError::find_mut(&mut err) {
+        error.message = format!("{prefix}: {}", error.message);
+        return err;
+    }
+    let message = format!("{prefix}: {err}");
+    err.context(message)
+}
+
+macro_rules! impl_host_fn {
+    ($arity:expr; $($arg:ident $idx:tt),*) => {
+        impl<Func, Ret, $($arg,)*> HostFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Ret + 'static,
+            Ret: IntoHostResult,
+            $($arg: FromValue,)*
+        {
+            const ARITY: usize = $arity;
+
+            #[allow(unused_variables)]
+            fn invoke(&self, name: &str, args: &[Value]) -> Result<Value> {
+                let result = (self)($(convert_arg::<$arg>(name, args, $idx)?),*);
+                result.into_host_result().map_err(|e| with_prefix(e, name))
+            }
+        }
+    };
+}
+
+impl_host_fn!(0;);
+impl_host_fn!(1; A 0);
+impl_host_fn!(2; A 0, B 1);
+impl_host_fn!(3; A 0, B 1, C 2);
+impl_host_fn!(4; A 0, B 1, C 2, D 3);
+impl_host_fn!(5; A 0, B 1, C 2, D 3, E 4);
+impl_host_fn!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Adapts a `HostFn` to the interpreter's `BuiltinFn` calling convention.
+pub(crate) struct HostBuiltin<F, Args> {
+    pub name: String,
+    pub f: F,
+    pub args: PhantomData<fn(Args)>,
+}
+
+impl<F, Args> std::fmt::Debug for HostBuiltin<F, Args> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_tuple("Builtin").field(&self.name).finish()
+    }
+}
+
+impl<F: HostFn<Args>, Args> BuiltinFn for HostBuiltin<F, Args> {
+    fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        if args.len() != F::ARITY {
+            raise!(
+                ArityError,
+                "{}: expected {} argument(s), got {}",
+                self.name,
+                F::ARITY,
+                args.len()
+            );
+        }
+        self.f.invoke(&self.name, args)
+    }
+}
+
+/// A host function that takes its arguments unconverted and checks them
+/// itself.
+pub(crate) struct RawHostBuiltin<F> {
+    pub name: String,
+    pub f: F,
+}
+
+impl<F> std::fmt::Debug for RawHostBuiltin<F> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_tuple("Builtin").field(&self.name).finish()
+    }
+}
+
+impl<F> BuiltinFn for RawHostBuiltin<F>
+where
+    F: Fn(&[Value]) -> Result<Value>,
+{
+    fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        (self.f)(args)
+    }
+}
@@ -0,0 +1,186 @@
+use crate::ast::Span;
+use crate::interpreter::builtins::sys::Exit;
+use crate::interpreter::limits::LimitError;
+use crate::interpreter::value::{NativeObject, Value};
+
+/// The category of a runtime error, for hosts and tests to match on instead
+/// of message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// An operation applied to a value of the wrong type.
+    TypeError,
+    /// An undefined variable or unknown member.
+    NameError,
+    /// A list position out of range, or an element taken from an empty list.
+    IndexError,
+    /// A missing dictionary key, such as one passed to `remove`.
+    KeyError,
+    /// A call with the wrong number of arguments.
+    ArityError,
+    /// An argument of the right type but an unusable value, such as a
+    /// negative count.
+    ValueError,
+    DivisionByZero,
+    /// Integer arithmetic whose result does not fit in an int.
+    Overflow,
+    /// An operation the host has not granted, such as reading a file
+    /// without `--allow-read`.
+    PermissionError,
+    /// An error raised by the script itself.
+    UserError,
+    /// The call depth limit was exceeded.
+    StackOverflow,
+    /// Anything else, such as I/O failures or errors from host functions.
+    RuntimeError,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A runtime error raised by the interpreter or a builtin. It travels inside
+/// `anyhow::Error` like any other error; hosts find it with
+/// `SludgeError::find`. Errors from host functions and I/O stay untyped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SludgeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// The innermost expression that failed, once known.
+    pub span: Option<Span>,
+    /// The calls the error passed through, innermost first.
+    pub trace: Vec<TraceFrame>,
+}
+
+/// A call that was active when an error was raised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFrame {
+    /// The callee as written at the call site, such as `factorial` or `map`.
+    pub function: String,
+    pub span: Span,
+}
+
+impl SludgeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            span: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Finds the runtime error inside `err`, looking through any context
+    /// added on the way up.
+    pub fn find(err: &anyhow::Error) -> Option<&SludgeError> {
+        err.downcast_ref::<SludgeError>()
+    }
+
+    pub(crate) fn find_mut(err: &mut anyhow::Error) -> Option<&mut SludgeError> {
+        err.downcast_mut::<SludgeError>()
+    }
+
+    /// Formats where the error was raised and the calls it passed through,
+    /// innermost first, one `at function (source:line:column)` line each.
+    /// `source` names the script, such as its file name.
+    pub fn traceback(&self, source: &str) -> String {
+        let location = |span: Option<Span>| match span {
+            Some(span) => format!("{source}:{span}"),
+            None => source.to_string(),
+        };
+        // Each frame names a function and where it was called; the error's
+        // own span lies inside the innermost function.
+        let mut lines = Vec::with_capacity(self.trace.len() + 1);
+        let mut span = self.span;
+        for frame in &self.trace {
+            lines.push(format!("  at {} ({})", frame.function, location(span)));
+            span = Some(frame.span);
+        }
+        lines.push(format!("  at {}", location(span)));
+        lines.join("\n")
+    }
+
+    /// The error a `catch` block receives for `err`, or `None` for errors
+    /// that must end the run: `exit`, interrupts and exhausted limits other
+    /// than the call depth.
+    pub(crate) fn catchable(err: &anyhow::Error) -> Option<SludgeError> {
+        if let Some(error) = SludgeError::find(err) {
+            return Some(error.clone());
+        }
+        if Exit::find(err).is_some() {
+            return None;
+        }
+        match LimitError::find(err) {
+            Some(LimitError::StackOverflow { .. }) => {
+                Some(SludgeError::new(ErrorKind::StackOverflow, err.to_string()))
+            }
+            Some(_) => None,
+            None => Some(SludgeError::new(ErrorKind::RuntimeError, err.to_string())),
+        }
+    }
+}
+
+/// Shows the message alone; the kind and location are for callers that
+/// want them.
+impl std::fmt::Display for SludgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SludgeError {}
+
+/// Builds an `anyhow::Error` holding a `SludgeError` of the given kind, with
+/// a `format!`-style message.
+macro_rules! sludge_error {
+    ($kind:ident, $($arg:tt)+) => {
+        anyhow::Error::from($crate::interpreter::error::SludgeError::new(
+            $crate::interpreter::error::ErrorKind::$kind,
+            format!($($arg)+),
+        ))
+    };
+}
+
+/// Returns early with a `SludgeError`, like `bail!` for typed errors.
+macro_rules! raise {
+    ($kind:ident, $($arg:tt)+) => {
+        return Err($crate::interpreter::error::sludge_error!($kind, $($arg)+))
+    };
+}
+
+pub(crate) use {raise, sludge_error};
+
+/// The value bound by `catch (e)`. Scripts read `e.kind`, `e.message` and
+/// `e.location` (`"line:column"`, or `null` when unknown), and can rethrow it
+/// with `throw e`.
+#[derive(Debug)]
+pub struct CaughtError {
+    pub error: SludgeError,
+}
+
+impl NativeObject for CaughtError {
+    fn type_name(&self) -> &str {
+        "error"
+    }
+
+    fn get_member(&self, field: &str) -> Option<Value> {
+        match field {
+            "kind" => Some(Value::String(self.error.kind.to_string())),
+            "message" => Some(Value::String(self.error.message.clone())),
+            "location" => Some(match self.error.span {
+                Some(span) => Value::String(span.to_string()),
+                None => Value::Null,
+            }),
+            _ => None,
+        }
+    }
+
+    fn call_method(&self, method: &str, _args: &[Value]) -> anyhow::Result<Value> {
+        raise!(NameError, "unknown member '{method}' on type error")
+    }
+
+    fn fmt_display(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.error.kind, self.error.message)
+    }
+}
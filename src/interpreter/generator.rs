@@ -0,0 +1,245 @@
+use crate::ast::*;
+use crate::interpreter::Interpreter;
+use crate::interpreter::error::raise;
+use crate::interpreter::value::{Value, ValueIterator};
+
+use anyhow::{Error, Result};
+
+/// The suspended state of a generator function.
+///
+/// Rust frames cannot be paused mid-evaluation, so statements that may
+/// `yield` are executed through an explicit stack of frames instead of the
+/// recursive `execute_statement`. Everything else, including whole
+/// statements that never yield, still runs on the regular interpreter.
+pub struct Generator {
+    frames: Vec<Frame>,
+}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Generator")
+            .field("depth", &self.frames.len())
+            .finish()
+    }
+}
+
+enum Frame {
+    Block {
+        statements: Vec<Statement>,
+        index: usize,
+        interpreter: Interpreter,
+    },
+    While {
+        condition: Expr,
+        body: Expr,
+        interpreter: Interpreter,
+    },
+    For {
+        condition: Option<Expr>,
+        update: Option<Statement>,
+        body: Expr,
+        interpreter: Interpreter,
+        started: bool,
+    },
+}
+
+enum Step {
+    Continue,
+    Push(Box<Frame>),
+    Yield(Value),
+    Return,
+}
+
+impl Generator {
+    /// Creates a generator that will run `body` inside `interpreter`'s scope,
+    /// which already holds the bound arguments. A `yield` inside `try` is
+    /// rejected here, before any of the body runs: the frames above cannot
+    /// catch errors or run `finally` blocks.
+    pub fn new(interpreter: Interpreter, body: &Expr) -> Result<Self> {
+        let statements = match body {
+            Expr::Block(statements) => statements.clone(),
+            other => vec![Statement::Expression(other.clone())],
+        };
+        if statements.iter().any(yields_in_try) {
+            raise!(RuntimeError, "`yield` is not supported inside `try`");
+        }
+        Ok(Self {
+            frames: vec![Frame::Block {
+                statements,
+                index: 0,
+                interpreter,
+            }],
+        })
+    }
+
+    fn resume(&mut self) -> Result<Option<Value>> {
+        loop {
+            let step = match self.frames.last_mut() {
+                None => return Ok(None),
+                Some(Frame::Block {
+                    statements,
+                    index,
+                    interpreter,
+                }) => match statements.get(*index) {
+                    Some(statement) => {
+                        if *index == 0 {
+                            interpreter.hoist(statements)?;
+                        }
+                        *index += 1;
+                        Self::step(interpreter, statement)?
+                    }
+                    None => {
+                        self.frames.pop();
+                        Step::Continue
+                    }
+                },
+                Some(Frame::While {
+                    condition,
+                    body,
+                    interpreter,
+                }) => {
+                    if interpreter.eval_expr(condition)?.to_bool()? {
+                        Step::Push(Box::new(Self::block_frame(interpreter, body)))
+                    } else {
+                        self.frames.pop();
+                        Step::Continue
+                    }
+                }
+                Some(Frame::For {
+                    condition,
+                    update,
+                    body,
+                    interpreter,
+                    started,
+                }) => {
+                    if *started && let Some(update) = update {
+                        interpreter.execute_statement(update)?;
+                    }
+                    *started = true;
+
+                    let proceed = match condition {
+                        Some(condition) => interpreter.eval_expr(condition)?.to_bool()?,
+                        None => true,
+                    };
+                    if proceed {
+                        Step::Push(Box::new(Self::block_frame(interpreter, body)))
+                    } else {
+                        self.frames.pop();
+                        Step::Continue
+                    }
+                }
+            };
+
+            match step {
+                Step::Continue => {}
+                Step::Push(frame) => self.frames.push(*frame),
+                Step::Yield(value) => return Ok(Some(value)),
+                Step::Return => {
+                    self.frames.clear();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn step(interpreter: &Interpreter, statement: &Statement) -> Result<Step> {
+        if !statement.contains_yield() {
+            return match interpreter.execute_statement(statement)? {
+                Value::Return { .. } => Ok(Step::Return),
+                _ => Ok(Step::Continue),
+            };
+        }
+
+        match statement {
+            Statement::Yield(expr) => Ok(Step::Yield(interpreter.eval_expr(expr)?)),
+            Statement::If {
+                condition,
+                then_stmt,
+                else_stmt,
+            } => {
+                if interpreter.eval_expr(condition)?.to_bool()? {
+                    Ok(Step::Push(Box::new(Self::block_frame(
+                        interpreter,
+                        then_stmt,
+                    ))))
+                } else if let Some(else_branch) = else_stmt {
+                    Ok(Step::Push(Box::new(Self::block_frame(
+                        interpreter,
+                        else_branch,
+                    ))))
+                } else {
+                    Ok(Step::Continue)
+                }
+            }
+            Statement::While { condition, body } => Ok(Step::Push(Box::new(Frame::While {
+                condition: condition.clone(),
+                body: (**body).clone(),
+                interpreter: interpreter.fork(),
+            }))),
+            Statement::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    interpreter.execute_statement(init)?;
+                }
+                Ok(Step::Push(Box::new(Frame::For {
+                    condition: condition.clone(),
+                    update: update.as_deref().cloned(),
+                    body: (**body).clone(),
+                    interpreter: interpreter.fork(),
+                    started: false,
+                })))
+            }
+            Statement::Expression(block) => {
+                Ok(Step::Push(Box::new(Self::block_frame(interpreter, block))))
+            }
+            _ => unreachable!("statement reported a yield it cannot contain"),
+        }
+    }
+
+    fn block_frame(interpreter: &Interpreter, body: &Expr) -> Frame {
+        let statements = match body {
+            Expr::Block(statements) => statements.clone(),
+            other => vec![Statement::Expression(other.clone())],
+        };
+        Frame::Block {
+            statements,
+            index: 0,
+            interpreter: interpreter.branch(),
+        }
+    }
+}
+
+/// Whether `statement` has a `yield` inside a `try`, without looking into
+/// nested functions.
+fn yields_in_try(statement: &Statement) -> bool {
+    let block = |body: &Expr| match body {
+        Expr::Block(statements) => statements.iter().any(yields_in_try),
+        _ => false,
+    };
+    match statement {
+        Statement::Try { .. } => statement.contains_yield(),
+        Statement::If {
+            then_stmt,
+            else_stmt,
+            ..
+        } => block(then_stmt) || else_stmt.as_ref().is_some_and(|e| block(e)),
+        Statement::While { body, .. } | Statement::For { body, .. } => block(body),
+        Statement::Expression(expr) => block(expr),
+        _ => false,
+    }
+}
+
+impl ValueIterator for Generator {
+    fn next(&mut self, _interpreter: &Interpreter) -> Result<Option<Value>, Error> {
+        let result = self.resume();
+        if result.is_err() {
+            // A generator that raised an error is finished.
+            self.frames.clear();
+        }
+        result
+    }
+}
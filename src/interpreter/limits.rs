@@ -0,0 +1,214 @@
+use crate::interpreter::value::Value;
+
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Caps on the resources a program may use, for running untrusted scripts.
+/// `None` leaves a resource unbounded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Nested function calls. Deep recursion would otherwise overflow the
+    /// host's stack, so this is bounded by default.
+    pub max_call_depth: Option<usize>,
+    /// Expressions evaluated per run.
+    pub max_steps: Option<u64>,
+    /// Wall-clock time per run.
+    pub max_duration: Option<Duration>,
+    /// Elements in a list, set, dict or tuple, and bytes in a string.
+    pub max_collection_size: Option<usize>,
+}
+
+impl Limits {
+    pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+}
+
+/// No limits besides the default call depth.
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_call_depth: Some(Self::DEFAULT_MAX_CALL_DEPTH),
+            max_steps: None,
+            max_duration: None,
+            max_collection_size: None,
+        }
+    }
+}
+
+/// Raised when a program exceeds one of its `Limits` or is cancelled through
+/// its `CancellationHandle`. Hosts can find it in an error chain with
+/// `LimitError::find`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    StackOverflow { depth: usize },
+    StepLimit { steps: u64 },
+    Timeout { duration: Duration },
+    CollectionTooLarge { size: usize, limit: usize },
+    Interrupted,
+}
+
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitError::StackOverflow { depth } => {
+                write!(f, "stack overflow: call depth exceeded {depth}")
+            }
+            LimitError::StepLimit { steps } => {
+                write!(f, "step limit exceeded: ran more than {steps} steps")
+            }
+            LimitError::Timeout { duration } => {
+                write!(f, "time limit exceeded: ran longer than {duration:?}")
+            }
+            LimitError::CollectionTooLarge { size, limit } => {
+                write!(
+                    f,
+                    "collection too large: {size} exceeds the limit of {limit}"
+                )
+            }
+            LimitError::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+impl LimitError {
+    /// Finds a limit violation anywhere in an error's context chain.
+    pub fn find(err: &anyhow::Error) -> Option<&LimitError> {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<LimitError>())
+    }
+}
+
+/// Requests that a running program stop. Clones share the request and can
+/// be sent to other threads or a signal handler; the program aborts with
+/// `LimitError::Interrupted` at its next step.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancellationHandle {
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// The underlying flag, for APIs that set one directly such as
+    /// `signal_hook::flag::register`.
+    pub fn flag(&self) -> &Arc<AtomicBool> {
+        &self.flag
+    }
+
+    /// Drops a pending request, such as one made between runs.
+    pub fn clear(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    /// Consumes a pending request, so it aborts only one run.
+    fn take(&self) -> bool {
+        self.is_cancelled() && self.flag.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// How often the clock is read, in steps; `Instant::now` is too slow to
+/// call on every expression.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Usage counters shared by every interpreter working on the same program.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    pub limits: Limits,
+    pub cancellation: CancellationHandle,
+    depth: Cell<usize>,
+    steps: Cell<u64>,
+    started: Cell<Instant>,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            cancellation: CancellationHandle::default(),
+            depth: Cell::new(0),
+            steps: Cell::new(0),
+            started: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Restarts the step count and clock, at the start of a run.
+    pub fn reset(&self) {
+        self.steps.set(0);
+        self.started.set(Instant::now());
+    }
+
+    /// Accounts for one evaluation step. Loop iterations and function calls
+    /// all evaluate expressions, so this is also where cancellation is seen.
+    pub fn tick(&self) -> Result<(), LimitError> {
+        if self.cancellation.take() {
+            return Err(LimitError::Interrupted);
+        }
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if let Some(max) = self.limits.max_steps
+            && steps > max
+        {
+            return Err(LimitError::StepLimit { steps: max });
+        }
+        if let Some(duration) = self.limits.max_duration
+            && steps.is_multiple_of(CLOCK_INTERVAL)
+            && self.started.get().elapsed() > duration
+        {
+            return Err(LimitError::Timeout { duration });
+        }
+        Ok(())
+    }
+
+    /// Enters a function call; the depth is released when the guard drops.
+    pub fn enter_call(&self) -> Result<CallGuard<'_>, LimitError> {
+        let depth = self.depth.get() + 1;
+        if let Some(max) = self.limits.max_call_depth
+            && depth > max
+        {
+            return Err(LimitError::StackOverflow { depth: max });
+        }
+        self.depth.set(depth);
+        Ok(CallGuard { budget: self })
+    }
+
+    /// Rejects collections and strings above the size limit.
+    pub fn check_size(&self, value: &Value) -> Result<(), LimitError> {
+        let size = match value {
+            Value::String(s) => s.len(),
+            Value::List { values } => values.borrow().len(),
+            Value::Tuple { values } => values.len(),
+            Value::Set { values } => values.borrow().len(),
+            Value::Dictionary { values } => values.borrow().len(),
+            _ => return Ok(()),
+        };
+        self.check_len(size)
+    }
+
+    /// Rejects a collection being built once it holds `size` elements, so
+    /// native loops stop before the finished value could be checked.
+    pub fn check_len(&self, size: usize) -> Result<(), LimitError> {
+        match self.limits.max_collection_size {
+            Some(limit) if size > limit => Err(LimitError::CollectionTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) struct CallGuard<'a> {
+    budget: &'a Budget,
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        self.budget.depth.set(self.budget.depth.get() - 1);
+    }
+}
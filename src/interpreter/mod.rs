@@ -1,36 +1,114 @@
 pub mod builtins;
+pub mod convert;
+pub mod error;
+pub mod generator;
+pub mod limits;
+pub mod signature;
 #[cfg(test)]
 mod tests;
 pub mod value;
 pub mod variable_scope;
 
 use crate::ast::*;
+use crate::interpreter::builtins::sys::Exit;
+use crate::interpreter::error::{
+    CaughtError, ErrorKind, SludgeError, TraceFrame, raise, sludge_error,
+};
+use crate::interpreter::generator::Generator;
+use crate::interpreter::limits::{Budget, CallGuard, CancellationHandle, LimitError, Limits};
+use crate::interpreter::signature::{NamedArgs, Signature};
+use crate::interpreter::value::Hashable;
 use crate::interpreter::value::NamedBuiltin;
 use crate::interpreter::value::NamedBuiltinWithInterpreter;
+use crate::interpreter::value::NativeMethod;
 use crate::interpreter::value::Value;
 
 use crate::interpreter::variable_scope::VariableScope;
 
-use anyhow::{Context, Result, anyhow, bail};
+use anyhow::{Context, Result};
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::rc::Rc;
 
+/// Remaining stack below which `eval_expr` switches to a new segment.
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
 pub struct Interpreter {
     pub(crate) variables: Rc<VariableScope>,
     pub(crate) stdout: Rc<RefCell<dyn Write>>,
+    pub(crate) budget: Rc<Budget>,
+    /// Whether this evaluates a (non-generator) function body, where
+    /// `return f(...)` is a tail call.
+    pub(crate) in_function: bool,
+}
+
+impl std::fmt::Debug for Interpreter {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Interpreter").finish_non_exhaustive()
+    }
 }
 
 impl Interpreter {
     pub fn new(variables: Rc<VariableScope>, stdout: Rc<RefCell<dyn Write>>) -> Self {
-        Self { variables, stdout }
+        Self::with_limits(variables, stdout, Limits::default())
+    }
+
+    pub fn with_limits(
+        variables: Rc<VariableScope>,
+        stdout: Rc<RefCell<dyn Write>>,
+        limits: Limits,
+    ) -> Self {
+        Self {
+            variables,
+            stdout,
+            budget: Rc::new(Budget::new(limits)),
+            in_function: false,
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.budget.limits
+    }
+
+    /// Creates another handle onto the same scope, output and budget.
+    pub(crate) fn fork(&self) -> Self {
+        self.with_scope(self.variables.clone())
+    }
+
+    /// Creates an interpreter for a child scope of this one.
+    pub(crate) fn branch(&self) -> Self {
+        self.with_scope(VariableScope::branch(&self.variables))
+    }
+
+    /// Creates an interpreter over `variables` sharing this one's output and
+    /// budget.
+    pub(crate) fn with_scope(&self, variables: Rc<VariableScope>) -> Self {
+        Self {
+            variables,
+            stdout: self.stdout.clone(),
+            budget: self.budget.clone(),
+            in_function: self.in_function,
+        }
+    }
+
+    /// A handle that aborts whatever this interpreter is running.
+    pub fn cancellation_handle(&self) -> CancellationHandle {
+        self.budget.cancellation.clone()
+    }
+
+    /// Restarts the step and time budget, so each run gets the full limits.
+    pub fn reset_budget(&self) {
+        self.budget.reset();
     }
 
     pub fn run_program(&self, program: &Program) -> Result<Value> {
+        self.reset_budget();
         self.execute_statements(&program.statements)
     }
 
-    fn type_name(v: &Value) -> &'static str {
+    pub(crate) fn type_name(v: &Value) -> &str {
         match v {
             Value::Null => "null",
             Value::Boolean(_) => "boolean",
@@ -42,7 +120,11 @@ impl Interpreter {
             Value::Dictionary { .. } => "dict",
             Value::Function { .. } => "function",
             Value::BuiltinFn(_) => "builtin",
+            Value::Iterator(_) => "iterator",
+            Value::Module { .. } => "module",
+            Value::Native(object) => object.type_name(),
             Value::Return { .. } => "return",
+            Value::TailCall { .. } => "tail call",
         }
     }
 
@@ -54,6 +136,12 @@ impl Interpreter {
             BinOp::Div => left.clone() / right.clone(),
             BinOp::Mod => left.clone() % right.clone(),
             BinOp::Pow => left.clone().pow(right.clone()),
+            BinOp::BitAnd => left.clone() & right.clone(),
+            BinOp::BitOr => left.clone() | right.clone(),
+            BinOp::BitXor => left.clone() ^ right.clone(),
+            BinOp::Shl => left.clone() << right.clone(),
+            BinOp::Shr => left.clone() >> right.clone(),
+            BinOp::In => right.contains(left),
 
             BinOp::Eq => Ok(Value::Boolean(left == right)),
             BinOp::Ne => Ok(Value::Boolean(left != right)),
@@ -63,14 +151,61 @@ impl Interpreter {
             BinOp::Ge => Ok(Value::Boolean(left >= right)),
             BinOp::And => Ok(Value::Boolean(left.to_bool()? && right.to_bool()?)),
             BinOp::Or => Ok(Value::Boolean(left.to_bool()? || right.to_bool()?)),
+            BinOp::Coalesce => Ok(match left {
+                Value::Null => right.clone(),
+                _ => left.clone(),
+            }),
         }
     }
 
     fn eval_expr(&self, expr: &Expr) -> Result<Value> {
+        self.budget.tick()?;
+        // Evaluation recurses through nested calls, so grow the stack on the
+        // heap instead of overflowing it; `max_call_depth` bounds the total.
+        let value = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+            self.eval_expr_unchecked(expr)
+        })
+        .map_err(|e| Self::locate(e, expr))?;
+        self.budget.check_size(&value)?;
+        Ok(value)
+    }
+
+    /// Records `expr` as where a runtime error was raised, unless a more
+    /// deeply nested expression already did. Untyped errors, such as those
+    /// from host functions, become `RuntimeError`s here so they carry a
+    /// location and trace too.
+    fn locate(err: anyhow::Error, expr: &Expr) -> anyhow::Error {
+        let Some(span) = expr.span() else {
+            return err;
+        };
+        let mut err = if SludgeError::find(&err).is_some()
+            || LimitError::find(&err).is_some()
+            || Exit::find(&err).is_some()
+        {
+            err
+        } else {
+            let error = SludgeError::new(ErrorKind::RuntimeError, err.to_string());
+            err.context(error)
+        };
+        if let Some(error) = SludgeError::find_mut(&mut err)
+            && error.span.is_none()
+        {
+            error.span = Some(span);
+        }
+        err
+    }
+
+    fn eval_expr_unchecked(&self, expr: &Expr) -> Result<Value> {
         match expr {
-            Expr::Member { target, field } => {
+            Expr::Member {
+                target,
+                field,
+                optional,
+                ..
+            } => {
                 let target = self.eval_expr(target)?;
                 match target {
+                    Value::Null if *optional => Ok(Value::Null),
                     Value::List { values } => match field.as_str() {
                         "join" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
                             name: "join",
@@ -100,45 +235,147 @@ impl Interpreter {
                         "map" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
                             name: "map",
                             this: Value::List { values },
-                            interpreter: Rc::new(Interpreter::new(
-                                VariableScope::branch(&self.variables),
-                                self.stdout.clone(),
-                            )),
+                            interpreter: Rc::new(self.branch()),
                             f: builtins::list::map,
                         }))),
                         "filter" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
                             name: "filter",
                             this: Value::List { values },
-                            interpreter: Rc::new(Interpreter::new(
-                                VariableScope::branch(&self.variables),
-                                self.stdout.clone(),
-                            )),
+                            interpreter: Rc::new(self.branch()),
                             f: builtins::list::filter,
                         }))),
                         "all" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
                             name: "all",
                             this: Value::List { values },
-                            interpreter: Rc::new(Interpreter::new(
-                                VariableScope::branch(&self.variables),
-                                self.stdout.clone(),
-                            )),
+                            interpreter: Rc::new(self.branch()),
                             f: builtins::list::all,
                         }))),
                         "any" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
                             name: "any",
                             this: Value::List { values },
-                            interpreter: Rc::new(Interpreter::new(
-                                VariableScope::branch(&self.variables),
-                                self.stdout.clone(),
-                            )),
+                            interpreter: Rc::new(self.branch()),
                             f: builtins::list::any,
                         }))),
+                        "iter" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "iter",
+                            this: Value::List { values },
+                            f: builtins::iterator::iter,
+                        }))),
                         "sum" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
                             name: "sum",
                             this: Value::List { values },
                             f: builtins::list::sum,
                         }))),
-                        other => bail!("unknown member '{}' on type list", other),
+                        "insert" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "insert",
+                            this: Value::List { values },
+                            f: builtins::list::insert,
+                        }))),
+                        "truncate" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "truncate",
+                            this: Value::List { values },
+                            f: builtins::list::truncate,
+                        }))),
+                        "has" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "has",
+                            this: Value::List { values },
+                            f: builtins::list::has,
+                        }))),
+                        "remove" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "remove",
+                            this: Value::List { values },
+                            f: builtins::list::remove,
+                        }))),
+                        "reverse" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "reverse",
+                            this: Value::List { values },
+                            f: builtins::list::reverse,
+                        }))),
+                        "sort" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "sort",
+                            this: Value::List { values },
+                            f: builtins::list::sort,
+                        }))),
+                        "chunks" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "chunks",
+                            this: Value::List { values },
+                            f: builtins::list::chunks,
+                        }))),
+                        "flatten" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "flatten",
+                            this: Value::List { values },
+                            f: builtins::list::flatten,
+                        }))),
+                        "zip" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "zip",
+                            this: Value::List { values },
+                            f: builtins::list::zip,
+                        }))),
+                        "enumerate" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "enumerate",
+                            this: Value::List { values },
+                            f: builtins::list::enumerate,
+                        }))),
+                        "index_of" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "index_of",
+                            this: Value::List { values },
+                            f: builtins::list::index_of,
+                        }))),
+                        "slice" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "slice",
+                            this: Value::List { values },
+                            f: builtins::list::slice,
+                        }))),
+                        "concat" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "concat",
+                            this: Value::List { values },
+                            f: builtins::list::concat,
+                        }))),
+                        "unique" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "unique",
+                            this: Value::List { values },
+                            f: builtins::list::unique,
+                        }))),
+                        "min" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "min",
+                            this: Value::List { values },
+                            f: builtins::list::min,
+                        }))),
+                        "max" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "max",
+                            this: Value::List { values },
+                            f: builtins::list::max,
+                        }))),
+                        "count" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "count",
+                            this: Value::List { values },
+                            f: builtins::list::count,
+                        }))),
+                        "sort_by" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "sort_by",
+                            this: Value::List { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::list::sort_by,
+                        }))),
+                        "reduce" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "reduce",
+                            this: Value::List { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::list::reduce,
+                        }))),
+                        "fold" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "fold",
+                            this: Value::List { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::list::fold,
+                        }))),
+                        "find" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "find",
+                            this: Value::List { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::list::find,
+                        }))),
+                        other => raise!(NameError, "unknown member '{}' on type list", other),
                     },
                     Value::Set { values } => match field.as_str() {
                         "has" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
@@ -176,7 +413,44 @@ impl Interpreter {
                             this: Value::Set { values },
                             f: builtins::set::length,
                         }))),
-                        other => bail!("unknown member '{}' on type set", other),
+                        "symmetric_difference" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "symmetric_difference",
+                            this: Value::Set { values },
+                            f: builtins::set::symmetric_difference,
+                        }))),
+                        "is_subset" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "is_subset",
+                            this: Value::Set { values },
+                            f: builtins::set::is_subset,
+                        }))),
+                        "is_superset" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "is_superset",
+                            this: Value::Set { values },
+                            f: builtins::set::is_superset,
+                        }))),
+                        "is_disjoint" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "is_disjoint",
+                            this: Value::Set { values },
+                            f: builtins::set::is_disjoint,
+                        }))),
+                        "to_list" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "to_list",
+                            this: Value::Set { values },
+                            f: builtins::set::to_list,
+                        }))),
+                        "map" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "map",
+                            this: Value::Set { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::set::map,
+                        }))),
+                        "filter" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "filter",
+                            this: Value::Set { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::set::filter,
+                        }))),
+                        other => raise!(NameError, "unknown member '{}' on type set", other),
                     },
                     Value::Dictionary { values } => match field.as_str() {
                         "get" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
@@ -214,9 +488,105 @@ impl Interpreter {
                             this: Value::Dictionary { values },
                             f: builtins::dict::length,
                         }))),
-                        other => bail!("unknown member '{}' on type dict", other),
+                        "has" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "has",
+                            this: Value::Dictionary { values },
+                            f: builtins::dict::has,
+                        }))),
+                        "merge" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "merge",
+                            this: Value::Dictionary { values },
+                            f: builtins::dict::merge,
+                        }))),
+                        "get_or" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "get_or",
+                            this: Value::Dictionary { values },
+                            f: builtins::dict::get_or,
+                        }))),
+                        "setdefault" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "setdefault",
+                            this: Value::Dictionary { values },
+                            f: builtins::dict::setdefault,
+                        }))),
+                        "map_values" => {
+                            Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                                name: "map_values",
+                                this: Value::Dictionary { values },
+                                interpreter: Rc::new(self.branch()),
+                                f: builtins::dict::map_values,
+                            })))
+                        }
+                        "filter" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "filter",
+                            this: Value::Dictionary { values },
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::dict::filter,
+                        }))),
+                        other => raise!(NameError, "unknown member '{}' on type dict", other),
+                    },
+                    Value::Iterator(iter) => match field.as_str() {
+                        "next" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "next",
+                            this: Value::Iterator(iter),
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::iterator::next,
+                        }))),
+                        "collect" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "collect",
+                            this: Value::Iterator(iter),
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::iterator::collect,
+                        }))),
+                        "take" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "take",
+                            this: Value::Iterator(iter),
+                            f: builtins::iterator::take,
+                        }))),
+                        "skip" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "skip",
+                            this: Value::Iterator(iter),
+                            f: builtins::iterator::skip,
+                        }))),
+                        "chain" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltin {
+                            name: "chain",
+                            this: Value::Iterator(iter),
+                            f: builtins::iterator::chain,
+                        }))),
+                        "map" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "map",
+                            this: Value::Iterator(iter),
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::iterator::map,
+                        }))),
+                        "filter" => Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                            name: "filter",
+                            this: Value::Iterator(iter),
+                            interpreter: Rc::new(self.branch()),
+                            f: builtins::iterator::filter,
+                        }))),
+                        "take_while" => {
+                            Ok(Value::BuiltinFn(Rc::new(NamedBuiltinWithInterpreter {
+                                name: "take_while",
+                                this: Value::Iterator(iter),
+                                interpreter: Rc::new(self.branch()),
+                                f: builtins::iterator::take_while,
+                            })))
+                        }
+                        other => raise!(NameError, "unknown member '{}' on type iterator", other),
+                    },
+                    Value::Module { name, members } => match members.get(field.as_str()) {
+                        Some(member) => Ok(member.clone()),
+                        None => raise!(NameError, "unknown member '{}' in module {}", field, name),
+                    },
+                    Value::Native(object) => match object.get_member(field) {
+                        Some(member) => Ok(member),
+                        None => Ok(Value::BuiltinFn(Rc::new(NativeMethod {
+                            object,
+                            name: field.clone(),
+                        }))),
                     },
-                    other => bail!(
+                    other => raise!(
+                        TypeError,
                         "member access not supported: type '{}' has no members",
                         Self::type_name(&other)
                     ),
@@ -226,6 +596,7 @@ impl Interpreter {
             Expr::Number(n) => Ok(Value::Int32(*n)),
             Expr::String(s) => Ok(Value::String(s.clone())),
             Expr::Boolean(b) => Ok(Value::Boolean(*b)),
+            Expr::Null => Ok(Value::Null),
 
             Expr::Tuple { values } => Ok({
                 let values: Vec<_> = values
@@ -235,13 +606,101 @@ impl Interpreter {
                 Value::Tuple { values }
             }),
 
-            Expr::Identifier(name) => self
+            Expr::ListLiteral { values } => {
+                let values: Vec<_> = values
+                    .iter()
+                    .map(|e| self.eval_expr(e))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::List {
+                    values: Rc::new(RefCell::new(values)),
+                })
+            }
+
+            Expr::SetLiteral { values } => {
+                let mut set = HashSet::with_capacity(values.len());
+                for e in values {
+                    let value = self.eval_expr(e)?;
+                    let key = Hashable::try_from(&value).with_context(|| {
+                        format!("set literal: value is not hashable (got {value})")
+                    })?;
+                    set.insert(key);
+                }
+                Ok(Value::Set {
+                    values: Rc::new(RefCell::new(set)),
+                })
+            }
+
+            Expr::DictLiteral { entries } => {
+                let mut map = HashMap::with_capacity(entries.len());
+                for (k, v) in entries {
+                    let key = self.eval_expr(k)?;
+                    let key = Hashable::try_from(&key).with_context(|| {
+                        format!("dict literal: key is not hashable (got {key})")
+                    })?;
+                    map.insert(key, self.eval_expr(v)?);
+                }
+                Ok(Value::Dictionary {
+                    values: Rc::new(RefCell::new(map)),
+                })
+            }
+
+            Expr::ListComprehension { element, clause } => {
+                let mut out = Vec::new();
+                self.eval_comprehension(clause, |interpreter| {
+                    out.push(interpreter.eval_expr(element)?);
+                    interpreter.budget.check_len(out.len())?;
+                    Ok(())
+                })?;
+                Ok(Value::List {
+                    values: Rc::new(RefCell::new(out)),
+                })
+            }
+
+            Expr::SetComprehension { element, clause } => {
+                let mut out = HashSet::new();
+                self.eval_comprehension(clause, |interpreter| {
+                    let value = interpreter.eval_expr(element)?;
+                    let key = Hashable::try_from(&value).with_context(|| {
+                        format!("set comprehension: value is not hashable (got {value})")
+                    })?;
+                    out.insert(key);
+                    interpreter.budget.check_len(out.len())?;
+                    Ok(())
+                })?;
+                Ok(Value::Set {
+                    values: Rc::new(RefCell::new(out)),
+                })
+            }
+
+            Expr::DictComprehension { key, value, clause } => {
+                let mut out = HashMap::new();
+                self.eval_comprehension(clause, |interpreter| {
+                    let k = interpreter.eval_expr(key)?;
+                    let k = Hashable::try_from(&k).with_context(|| {
+                        format!("dict comprehension: key is not hashable (got {k})")
+                    })?;
+                    out.insert(k, interpreter.eval_expr(value)?);
+                    interpreter.budget.check_len(out.len())?;
+                    Ok(())
+                })?;
+                Ok(Value::Dictionary {
+                    values: Rc::new(RefCell::new(out)),
+                })
+            }
+
+            Expr::Identifier { name, .. } => self
                 .variables
                 .get(name)
-                .ok_or_else(|| anyhow!("undefined variable '{}'", name)),
+                .ok_or_else(|| sludge_error!(NameError, "undefined variable '{}'", name)),
 
-            Expr::BinaryOp { op, left, right } => match op {
+            Expr::BinaryOp {
+                op, left, right, ..
+            } => match op {
                 BinOp::And | BinOp::Or => self.eval_logical_op(op, left, right),
+                BinOp::Coalesce => match self.eval_expr(left)? {
+                    Value::Null => self.eval_expr(right),
+                    value => Ok(value),
+                },
                 _ => {
                     let lval = self.eval_expr(left)?;
                     let rval = self.eval_expr(right)?;
@@ -249,33 +708,49 @@ impl Interpreter {
                 }
             },
 
-            Expr::UnaryOp { op, operand } => {
+            Expr::UnaryOp { op, operand, .. } => {
                 let val = self.eval_expr(operand)?;
                 self.eval_unary_op(op, &val)
             }
 
-            Expr::Call { target, args } => self.eval_call(target, args),
+            Expr::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                if self.eval_expr(condition)?.to_bool()? {
+                    self.eval_expr(then)
+                } else {
+                    self.eval_expr(otherwise)
+                }
+            }
+
+            Expr::Call {
+                target,
+                args,
+                named,
+                span,
+            } => self.eval_call(target, args, named, *span),
 
             Expr::Function {
                 arguments,
                 statement,
+                generator,
             } => Ok(Value::Function {
-                arguments: arguments
-                    .iter()
-                    .map(|argument| match argument {
-                        AssignTarget::Identifier(name) => name.to_string(),
-                    })
-                    .collect(),
+                name: None,
+                signature: Rc::new(Signature::from_params(arguments)),
                 scope: VariableScope::branch(&self.variables),
-                statement: statement.clone(),
+                statement: Rc::new((**statement).clone()),
+                generator: *generator,
             }),
 
             Expr::Block(statements) => {
-                let interpreter =
-                    Interpreter::new(VariableScope::branch(&self.variables), self.stdout.clone());
+                let interpreter = self.branch();
+                interpreter.hoist(statements)?;
 
                 for statement in statements {
-                    if let Ok(Value::Return { value }) = interpreter.execute_statement(statement) {
+                    if let Value::Return { value } = interpreter.execute_statement(statement)? {
                         return Ok(Value::Return { value });
                     }
                 }
@@ -285,60 +760,310 @@ impl Interpreter {
         }
     }
 
-    fn eval_call(&self, target: &Expr, args: &[Expr]) -> Result<Value> {
-        match self.eval_expr(target)? {
-            Value::BuiltinFn(f) => {
-                let evaluated_args: Vec<_> = args
-                    .iter()
-                    .map(|e| self.eval_expr(e))
-                    .collect::<Result<_, _>>()?;
-                f.call(evaluated_args.as_slice())
+    fn eval_call(
+        &self,
+        target: &Expr,
+        args: &[Expr],
+        named: &[(String, Expr)],
+        span: Span,
+    ) -> Result<Value> {
+        let callable = self.eval_expr(target)?;
+        if Self::skips_call(target, &callable) {
+            return Ok(Value::Null);
+        }
+        let (args, named) = self.eval_args(args, named)?;
+        self.call_with_named(&callable, args, named).map_err(|e| {
+            let frame = TraceFrame {
+                function: Self::callee_name(target, &callable),
+                span,
+            };
+            Self::annotate_call(e, frame)
+        })
+    }
+
+    /// Whether a call is skipped for being made through `?.` on null, as
+    /// in `a?.f(x)`, which is null without evaluating `x`.
+    fn skips_call(target: &Expr, callable: &Value) -> bool {
+        matches!(
+            (target, callable),
+            (Expr::Member { optional: true, .. }, Value::Null)
+        )
+    }
+
+    /// Evaluates a call's positional and then its named arguments.
+    fn eval_args(
+        &self,
+        args: &[Expr],
+        named: &[(String, Expr)],
+    ) -> Result<(Vec<Value>, NamedArgs)> {
+        let args = args
+            .iter()
+            .map(|e| self.eval_expr(e))
+            .collect::<Result<_>>()?;
+        let named = named
+            .iter()
+            .map(|(name, e)| Ok((name.clone(), self.eval_expr(e)?)))
+            .collect::<Result<_>>()?;
+        Ok((args, named))
+    }
+
+    /// Adds a failed call to an error's trace. Errors raised by the call
+    /// itself, such as a wrong argument count, have no location yet and are
+    /// reported at the call site instead.
+    fn annotate_call(mut err: anyhow::Error, frame: TraceFrame) -> anyhow::Error {
+        if let Some(error) = SludgeError::find_mut(&mut err) {
+            match error.span {
+                Some(_) => error.trace.push(frame),
+                None => error.span = Some(frame.span),
             }
+        }
+        err
+    }
+
+    /// Names a callee for stack traces: a function's own name, or else the
+    /// callee as written at the call site.
+    fn callee_name(target: &Expr, callable: &Value) -> String {
+        match (callable, target) {
+            (
+                Value::Function {
+                    name: Some(name), ..
+                },
+                _,
+            ) => name.clone(),
+            (_, Expr::Identifier { name, .. }) => name.clone(),
+            (_, Expr::Member { field, .. }) => field.clone(),
+            _ => "<anonymous>".to_string(),
+        }
+    }
+
+    /// Gives an anonymous function a name.
+    fn named(value: Value, name: &str) -> Value {
+        match value {
             Value::Function {
-                arguments,
+                name: None,
+                signature,
                 statement,
                 scope,
-            } => {
-                if arguments.len() != args.len() {
-                    bail!(
-                        "function expected {} argument(s), got {}",
-                        arguments.len(),
-                        args.len()
-                    );
-                }
+                generator,
+            } => Value::Function {
+                name: Some(name.to_string()),
+                signature,
+                statement,
+                scope,
+                generator,
+            },
+            other => other,
+        }
+    }
 
-                let evaluated_args: Vec<_> = args
-                    .iter()
-                    .map(|e| self.eval_expr(e))
-                    .collect::<Result<_, _>>()?;
+    /// Declares the `fn name(...)` functions of a block before any of its
+    /// statements run, so they can call each other regardless of order.
+    /// Hosts that run statements one at a time, such as a REPL, call this
+    /// first: executing a declaration does nothing.
+    pub fn hoist(&self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            if let Statement::FunctionDeclaration { name, function } = statement {
+                self.declare_function(name, function)?;
+            }
+        }
+        Ok(())
+    }
 
-                let interpreter =
-                    Interpreter::new(VariableScope::branch(&scope), self.stdout.clone());
+    fn declare_function(&self, name: &str, function: &Expr) -> Result<()> {
+        let value = Self::named(self.eval_expr(function)?, name);
+        self.variables.declare(name.to_string(), value);
+        Ok(())
+    }
 
-                for (param, value) in arguments.iter().cloned().zip(evaluated_args) {
-                    interpreter.variables.declare(param, value);
-                }
+    /// Invokes any callable value with already-evaluated arguments. This is
+    /// the single call path shared by call expressions and builtins that
+    /// accept callbacks.
+    ///
+    /// Calls in tail position come back as `Value::TailCall` and are run by
+    /// looping here rather than recursing, so tail-recursive functions use
+    /// constant stack and call depth.
+    pub(crate) fn call_value(&self, callable: &Value, args: &[Value]) -> Result<Value> {
+        self.call_with_named(callable, args.to_vec(), Vec::new())
+    }
 
-                let result = interpreter
-                    .eval_expr(&statement)
-                    .with_context(|| "function evaluation failed")?;
+    /// `call_value` with `name: value` arguments as well, bound to the
+    /// callee's parameters by name.
+    pub(crate) fn call_with_named(
+        &self,
+        callable: &Value,
+        mut args: Vec<Value>,
+        mut named: NamedArgs,
+    ) -> Result<Value> {
+        let mut callable = callable.clone();
+        let mut call_guard = None;
+        // The tail call being run, which has no `eval_call` of its own to
+        // locate its errors.
+        let mut tail_frame = None;
 
-                match result {
-                    Value::Return { value } => Ok(*value),
-                    other => bail!(
-                        "function must `return` a value (got {} of type {})",
-                        other,
-                        Self::type_name(&other)
-                    ),
+        loop {
+            let result = self.call_once(&callable, args, named, &mut call_guard);
+            let result = match tail_frame.take() {
+                Some(frame) => result.map_err(|e| Self::annotate_call(e, frame)),
+                None => result,
+            };
+            match result? {
+                Value::TailCall {
+                    callable: next,
+                    args: next_args,
+                    named: next_named,
+                    frame,
+                } => {
+                    callable = *next;
+                    args = next_args;
+                    named = next_named;
+                    tail_frame = Some(frame);
                 }
+                value => return Ok(value),
             }
-            other => bail!(
+        }
+    }
+
+    /// Runs one function body, or a builtin, returning the value it returns.
+    /// The depth guard is taken once and held across the tail calls of a
+    /// `call_value` loop.
+    fn call_once<'a>(
+        &'a self,
+        callable: &Value,
+        args: Vec<Value>,
+        named: NamedArgs,
+        call_guard: &mut Option<CallGuard<'a>>,
+    ) -> Result<Value> {
+        let (name, signature, statement, scope, generator) = match callable {
+            Value::BuiltinFn(f) => return f.call_in(self, &args, named),
+            Value::Function {
+                name,
+                signature,
+                statement,
+                scope,
+                generator,
+            } => (name, signature, statement, scope, *generator),
+            other => raise!(
+                TypeError,
                 "call target is not callable (got type {})",
+                Self::type_name(other)
+            ),
+        };
+
+        let mut bound = signature.bind(name.as_deref().unwrap_or("fn"), args, named)?;
+
+        if call_guard.is_none() {
+            *call_guard = Some(self.budget.enter_call()?);
+        }
+        let mut interpreter = self.with_scope(VariableScope::branch(scope));
+
+        // Defaults are evaluated at each call, in the function's scope, so
+        // they can refer to the parameters before them.
+        for (param, slot) in signature.params.iter().zip(bound.slots.iter_mut()) {
+            let value = match (slot.take(), &param.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => interpreter.eval_expr(default)?,
+                (None, None) => Value::Null,
+            };
+            interpreter.variables.declare(param.name.clone(), value);
+        }
+        if let Some(rest) = &signature.rest {
+            interpreter
+                .variables
+                .declare(rest.clone(), bound.rest_list());
+        }
+
+        interpreter.in_function = !generator;
+        if generator {
+            return Ok(Value::Iterator(Rc::new(RefCell::new(Generator::new(
+                interpreter,
+                statement,
+            )?))));
+        }
+
+        match interpreter.eval_expr(statement)? {
+            Value::Return { value } => Ok(*value),
+            other => raise!(
+                TypeError,
+                "function must `return` a value (got {} of type {})",
+                other,
                 Self::type_name(&other)
             ),
         }
     }
 
+    /// Runs `body` once per element of the clause's iterable that passes its
+    /// condition. Every iteration gets a fresh child scope, so closures created
+    /// in the body capture that iteration's binding.
+    fn eval_comprehension(
+        &self,
+        clause: &ComprehensionClause,
+        mut body: impl FnMut(&Interpreter) -> Result<()>,
+    ) -> Result<()> {
+        let iterable = self.eval_expr(&clause.iterable)?;
+        for item in self.iterate(&iterable)? {
+            let interpreter = self.branch();
+            interpreter.declare_target(&clause.target, item)?;
+
+            if let Some(condition) = &clause.condition
+                && !interpreter.eval_expr(condition)?.to_bool()?
+            {
+                continue;
+            }
+            body(&interpreter)?;
+        }
+        Ok(())
+    }
+
+    /// Snapshots the elements of a collection for iteration, draining
+    /// iterators. Dictionaries yield their keys.
+    fn iterate(&self, value: &Value) -> Result<Vec<Value>> {
+        match value {
+            Value::Iterator(iter) => builtins::iterator::drain(iter, self, "comprehension"),
+            Value::List { values } => Ok(values.borrow().clone()),
+            Value::Tuple { values } => Ok(values.clone()),
+            Value::Set { values } => Ok(values.borrow().iter().map(|h| h.as_value()).collect()),
+            Value::Dictionary { values } => {
+                Ok(values.borrow().keys().map(|h| h.as_value()).collect())
+            }
+            other => raise!(
+                TypeError,
+                "type '{}' is not iterable",
+                Self::type_name(other)
+            ),
+        }
+    }
+
+    /// Declares `target` in the current scope, destructuring tuples
+    /// element-wise.
+    fn declare_target(&self, target: &AssignTarget, value: Value) -> Result<()> {
+        match target {
+            AssignTarget::Identifier(name) => {
+                self.variables.declare(name.clone(), value);
+                Ok(())
+            }
+            AssignTarget::Tuple(targets) => match value {
+                Value::Tuple { values } if values.len() == targets.len() => {
+                    for (target, value) in targets.iter().zip(values) {
+                        self.declare_target(target, value)?;
+                    }
+                    Ok(())
+                }
+                Value::Tuple { values } => raise!(
+                    ValueError,
+                    "cannot unpack tuple of length {} into {} names",
+                    values.len(),
+                    targets.len()
+                ),
+                other => raise!(
+                    TypeError,
+                    "cannot unpack value of type '{}' into {} names",
+                    Self::type_name(&other),
+                    targets.len()
+                ),
+            },
+        }
+    }
+
     fn eval_logical_op(&self, op: &BinOp, left: &Expr, right: &Expr) -> Result<Value> {
         let lval = self.eval_expr(left)?;
         let lbool = lval.to_bool()?;
@@ -372,6 +1097,7 @@ impl Interpreter {
     }
 
     fn execute_statements(&self, statements: &[Statement]) -> Result<Value> {
+        self.hoist(statements)?;
         for stmt in statements {
             self.execute_statement(stmt)?;
         }
@@ -393,28 +1119,37 @@ impl Interpreter {
                 let new_value = self.eval_expr(value)?;
                 match target {
                     AssignTarget::Identifier(name) => {
-                        let final_value = match op {
-                            AssignOp::Assign => new_value,
+                        let final_value = match op.binary_op() {
+                            None => new_value,
+                            Some(op) => {
+                                let current = self.variables.get(name).ok_or_else(|| {
+                                    sludge_error!(NameError, "'{}' is an undefined variable!", name)
+                                })?;
+                                self.eval_binary_op(&op, &current, &new_value)?
+                            }
                         };
                         match self.variables.set(name.clone(), final_value) {
                             Some(_) => Ok(Value::Null),
-                            None => Err(anyhow!("'{}' is an undefined variable!", name)),
+                            None => raise!(NameError, "'{}' is an undefined variable!", name),
                         }
                     }
+                    AssignTarget::Tuple(_) => unreachable!("the parser only assigns to names"),
                 }
             }
-            Statement::Declaration { target, op, value } => {
-                let new_value = self.eval_expr(value)?;
-                match target {
-                    AssignTarget::Identifier(name) => {
-                        let final_value = match op {
-                            AssignOp::Assign => new_value,
-                        };
-                        self.variables.declare(name.clone(), final_value);
+            Statement::Declaration { target, value, .. } => {
+                let final_value = self.eval_expr(value)?;
+                // `let f = fn(...)` names the function after its binding.
+                let final_value = match (target, value) {
+                    (AssignTarget::Identifier(name), Expr::Function { .. }) => {
+                        Self::named(final_value, name)
                     }
-                }
+                    _ => final_value,
+                };
+                self.declare_target(target, final_value)?;
                 Ok(Value::Null)
             }
+            // Declared when the enclosing block was hoisted.
+            Statement::FunctionDeclaration { .. } => Ok(Value::Null),
             Statement::If {
                 condition,
                 then_stmt,
@@ -464,9 +1199,88 @@ impl Interpreter {
                 }
                 Ok(Value::Null)
             }
+            // Inside a function, `return f(...)` hands the call back to
+            // `call_value` instead of nesting it.
+            Statement::Return(Expr::Call {
+                target,
+                args,
+                named,
+                span,
+            }) if self.in_function => {
+                let callable = self.eval_expr(target)?;
+                if Self::skips_call(target, &callable) {
+                    return Ok(Value::Return {
+                        value: Box::new(Value::Null),
+                    });
+                }
+                let (args, named) = self.eval_args(args, named)?;
+                let frame = TraceFrame {
+                    function: Self::callee_name(target, &callable),
+                    span: *span,
+                };
+                Ok(Value::Return {
+                    value: Box::new(Value::TailCall {
+                        callable: Box::new(callable),
+                        args,
+                        named,
+                        frame,
+                    }),
+                })
+            }
             Statement::Return(expr) => Ok(Value::Return {
                 value: Box::new(self.eval_expr(expr)?),
             }),
+            Statement::Yield(_) => {
+                raise!(RuntimeError, "`yield` is only allowed inside a function")
+            }
+            Statement::Throw { value, span } => {
+                let value = self.eval_expr(value)?;
+                // Rethrowing a caught error keeps its kind and location.
+                let rethrown = match &value {
+                    Value::Native(object) => (object.as_ref() as &dyn std::any::Any)
+                        .downcast_ref::<CaughtError>()
+                        .map(|caught| caught.error.clone()),
+                    _ => None,
+                };
+                let error = rethrown.unwrap_or_else(|| SludgeError {
+                    span: Some(*span),
+                    ..SludgeError::new(ErrorKind::UserError, value.to_string())
+                });
+                Err(error.into())
+            }
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                // A call must finish inside the body for its errors to be
+                // caught, so `return f(...)` is not a tail call here.
+                let mut guarded = self.fork();
+                guarded.in_function = false;
+
+                let mut result = guarded.eval_expr(body);
+                let caught = match (&result, catch) {
+                    (Err(err), Some(catch)) => SludgeError::catchable(err).map(|e| (catch, e)),
+                    _ => None,
+                };
+                if let Some((catch, error)) = caught {
+                    let handler = guarded.branch();
+                    handler.variables.declare(
+                        catch.name.clone(),
+                        Value::Native(Rc::new(CaughtError { error })),
+                    );
+                    result = handler.eval_expr(&catch.body);
+                }
+
+                // A `return` or error in `finally` replaces the outcome of
+                // the body.
+                if let Some(finally) = finally
+                    && let Value::Return { value } = self.eval_expr(finally)?
+                {
+                    return Ok(Value::Return { value });
+                }
+                result
+            }
             Statement::Expression(expr) => self.eval_expr(expr),
         }
     }
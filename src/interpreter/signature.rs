@@ -0,0 +1,206 @@
+use crate::ast::{Expr, Param};
+use crate::interpreter::error::raise;
+use crate::interpreter::value::{BuiltinFn, Value};
+
+use anyhow::{Error, Result};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The `name: value` arguments of a call, in the order written.
+pub type NamedArgs = Vec<(String, Value)>;
+
+/// The parameters a callable accepts. Calls are bound against it, so
+/// arity errors can show what was expected.
+#[derive(Debug, Clone, Default)]
+pub struct Signature {
+    pub params: Vec<Parameter>,
+    /// Collects the extra positional arguments into a list.
+    pub rest: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    /// Whether the argument may be left out.
+    pub optional: bool,
+    /// For script functions, evaluated in place of a left out argument.
+    pub default: Option<Rc<Expr>>,
+}
+
+/// Call arguments matched to a signature.
+#[derive(Debug)]
+pub struct Bound {
+    /// One slot per parameter, `None` where the argument was left out.
+    pub slots: Vec<Option<Value>>,
+    pub rest: Vec<Value>,
+}
+
+impl Signature {
+    /// Declares a builtin's parameters. A trailing `?` marks an optional
+    /// one and a leading `...` the rest parameter, as in
+    /// `["start", "end?", "step?"]`.
+    pub fn new(params: &[&str]) -> Self {
+        let mut signature = Signature::default();
+        for param in params {
+            if let Some(rest) = param.strip_prefix("...") {
+                signature.rest = Some(rest.to_string());
+            } else {
+                let (name, optional) = match param.strip_suffix('?') {
+                    Some(name) => (name, true),
+                    None => (*param, false),
+                };
+                signature.params.push(Parameter {
+                    name: name.to_string(),
+                    optional,
+                    default: None,
+                });
+            }
+        }
+        signature
+    }
+
+    /// The signature of a function literal.
+    pub(crate) fn from_params(params: &[Param]) -> Self {
+        let mut signature = Signature::default();
+        for param in params {
+            if param.rest {
+                signature.rest = Some(param.name.clone());
+            } else {
+                signature.params.push(Parameter {
+                    name: param.name.clone(),
+                    optional: param.default.is_some(),
+                    default: param.default.clone().map(Rc::new),
+                });
+            }
+        }
+        signature
+    }
+
+    /// Matches positional and named arguments to the parameters of the
+    /// callable `name`, raising an `ArityError` naming the signature when
+    /// they do not fit.
+    pub fn bind(&self, name: &str, args: Vec<Value>, named: NamedArgs) -> Result<Bound> {
+        let mut slots: Vec<Option<Value>> = vec![None; self.params.len()];
+        let mut rest = Vec::new();
+
+        let given = args.len();
+        for (idx, value) in args.into_iter().enumerate() {
+            match slots.get_mut(idx) {
+                Some(slot) => *slot = Some(value),
+                None if self.rest.is_some() => rest.push(value),
+                None => {
+                    let bound = if self.params.iter().any(|p| p.optional) {
+                        "at most "
+                    } else {
+                        ""
+                    };
+                    raise!(
+                        ArityError,
+                        "{name}{self}: expected {bound}{} argument(s), got {given}",
+                        self.params.len()
+                    );
+                }
+            }
+        }
+
+        for (key, value) in named {
+            let Some(idx) = self.params.iter().position(|p| p.name == key) else {
+                raise!(ArityError, "{name}{self}: unknown argument '{key}'");
+            };
+            if slots[idx].is_some() {
+                raise!(
+                    ArityError,
+                    "{name}{self}: argument '{key}' given more than once"
+                );
+            }
+            slots[idx] = Some(value);
+        }
+
+        for (param, slot) in self.params.iter().zip(&slots) {
+            if slot.is_none() && !param.optional {
+                raise!(
+                    ArityError,
+                    "{name}{self}: missing argument '{}'",
+                    param.name
+                );
+            }
+        }
+
+        Ok(Bound { slots, rest })
+    }
+}
+
+impl Bound {
+    /// Flattens the arguments for a builtin: left out arguments become
+    /// `null`, except trailing ones, which are dropped.
+    pub fn into_args(self) -> Vec<Value> {
+        let mut slots = self.slots;
+        if self.rest.is_empty() {
+            while slots.last().is_some_and(Option::is_none) {
+                slots.pop();
+            }
+        }
+        let mut args: Vec<Value> = slots
+            .into_iter()
+            .map(|slot| slot.unwrap_or(Value::Null))
+            .collect();
+        args.extend(self.rest);
+        args
+    }
+
+    /// The rest arguments as a list value.
+    pub fn rest_list(&mut self) -> Value {
+        Value::List {
+            values: Rc::new(RefCell::new(std::mem::take(&mut self.rest))),
+        }
+    }
+}
+
+/// Shows the parameter list, such as `(start, end?, step?)`.
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| {
+                if p.optional {
+                    format!("{}?", p.name)
+                } else {
+                    p.name.clone()
+                }
+            })
+            .collect();
+        if let Some(rest) = &self.rest {
+            params.push(format!("...{rest}"));
+        }
+        write!(f, "({})", params.join(", "))
+    }
+}
+
+/// A builtin that declares its signature, so calls to it are checked and
+/// may pass named arguments.
+pub struct SignedBuiltin<F> {
+    pub name: String,
+    pub signature: Signature,
+    pub f: F,
+}
+
+impl<F> std::fmt::Debug for SignedBuiltin<F> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_tuple("Builtin").field(&self.name).finish()
+    }
+}
+
+impl<F> BuiltinFn for SignedBuiltin<F>
+where
+    F: Fn(&[Value]) -> Result<Value>,
+{
+    fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        self.call_named(args, Vec::new())
+    }
+
+    fn call_named(&self, args: &[Value], named: NamedArgs) -> Result<Value, Error> {
+        let bound = self.signature.bind(&self.name, args.to_vec(), named)?;
+        (self.f)(&bound.into_args())
+    }
+}
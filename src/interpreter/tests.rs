@@ -1,6 +1,11 @@
+use crate::ast::Span;
+use crate::ast::lint::lint;
 use crate::ast::parser::parse_program;
-use crate::interpreter::{Interpreter, VariableScope};
+use crate::interpreter::error::{ErrorKind, SludgeError};
+use crate::interpreter::limits::{LimitError, Limits};
+use crate::interpreter::{Interpreter, Value, VariableScope, builtins};
 use std::cell::RefCell;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 fn run_and_capture(src: &str) -> anyhow::Result<String> {
@@ -11,12 +16,24 @@ fn run_and_capture(src: &str) -> anyhow::Result<String> {
 }
 
 fn run_and_capture_err(src: &str) -> String {
+    format!("{}", run_and_capture_error(src))
+}
+
+fn run_and_capture_error(src: &str) -> anyhow::Error {
     let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
     match parse_program(src)
         .and_then(|p| Interpreter::new(VariableScope::new(), buffer.clone()).run_program(&p))
     {
         Ok(_) => panic!("expected program to error, but it succeeded"),
-        Err(e) => format!("{}", e),
+        Err(e) => e,
+    }
+}
+
+fn run_and_capture_sludge_err(src: &str) -> SludgeError {
+    let err = run_and_capture_error(src);
+    match SludgeError::find(&err) {
+        Some(error) => error.clone(),
+        None => panic!("expected a SludgeError, got: {err:#}"),
     }
 }
 
@@ -456,6 +473,27 @@ fn test_dict() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_dict_remove_missing_key_error() -> anyhow::Result<()> {
+    let err = run_and_capture_sludge_err(r#"dict(("a", 1)).remove("b")"#);
+    assert_eq!(err.kind, ErrorKind::KeyError);
+    assert_eq!(err.message, r#"remove: key "b" is not in the dictionary"#);
+
+    let out = run_and_capture(
+        r#"
+            let d = {"a": 1}
+            try {
+                d.remove("b")
+            } catch (e) {
+                print(e.kind) // KeyError
+            }
+            print(d.remove("a"), d.length()) // 1 0
+        "#,
+    )?;
+    assert_eq!(out, ["KeyError", "1 0", ""].join("\n"));
+    Ok(())
+}
+
 #[test]
 fn test_set_mutations() -> anyhow::Result<()> {
     let out = run_and_capture(
@@ -589,49 +627,46 @@ fn test_ensure_return_short_circuit() -> anyhow::Result<()> {
 
 #[test]
 fn test_unknown_identifier_errors() {
-    let err = run_and_capture_err(
+    let err = run_and_capture_sludge_err(
         r#"
             print(does_not_exist)
         "#,
     );
-    // From interpreter: "undefined variable '...'"
-    assert!(
-        err.contains("undefined variable") && err.contains("does_not_exist"),
-        "unexpected error: {err}"
-    );
+    assert_eq!(err.kind, ErrorKind::NameError);
+    assert_eq!(err.message, "undefined variable 'does_not_exist'");
 }
 
 #[test]
 fn test_member_unknown_and_wrong_receiver_errors() {
     // Unknown member on list
-    let err1 = run_and_capture_err(
+    let err1 = run_and_capture_sludge_err(
         r#"
             let xs = list(1,2,3)
             print(xs.whoopsies())
         "#,
     );
-    assert!(
-        err1.contains("unknown member") && err1.contains("list"),
-        "unexpected error: {err1}"
-    );
+    assert_eq!(err1.kind, ErrorKind::NameError);
+    assert_eq!(err1.message, "unknown member 'whoopsies' on type list");
 
     // Member access on a number (unsupported receiver type)
-    let err2 = run_and_capture_err(
+    let err2 = run_and_capture_sludge_err(
         r#"
             let fortyTwo = 42
             print(fortyTwo.length())
         "#,
     );
+    assert_eq!(err2.kind, ErrorKind::TypeError);
     assert!(
-        err2.contains("member access not supported") || err2.contains("has no members"),
-        "unexpected error: {err2}"
+        err2.message.contains("has no members"),
+        "unexpected error: {}",
+        err2.message
     );
 }
 
 #[test]
 fn test_function_must_return_error() {
     // Your stricter rule: user functions must `return`
-    let err = run_and_capture_err(
+    let err = run_and_capture_sludge_err(
         r#"
             let f = fn(a) {
                 a + 1 // no explicit return
@@ -639,13 +674,18 @@ fn test_function_must_return_error() {
             print(f(2))
         "#,
     );
-    assert!(err.contains("must `return`"), "unexpected error: {err}");
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert!(
+        err.message.contains("must `return`"),
+        "unexpected error: {}",
+        err.message
+    );
 }
 
 #[test]
 fn test_lambda_must_return_in_map_error() {
     // HOFs (map/filter/all/any) are strict about explicit `return`
-    let err = run_and_capture_err(
+    let err = run_and_capture_sludge_err(
         r#"
             let xs = list(1,2,3)
             // lambda missing `return` should error
@@ -653,24 +693,28 @@ fn test_lambda_must_return_in_map_error() {
             print(ys)
         "#,
     );
+    assert_eq!(err.kind, ErrorKind::TypeError);
     assert!(
-        err.contains("map") && err.contains("must `return`"),
-        "unexpected error: {err}"
+        err.message.contains("must `return`"),
+        "unexpected error: {}",
+        err.message
     );
 }
 
 #[test]
 fn test_lambda_must_return_in_filter_error() {
-    let err = run_and_capture_err(
+    let err = run_and_capture_sludge_err(
         r#"
             let xs = list(1,2,3)
             let ys = xs.filter(fn(item) { item > 1 })
             print(ys)
         "#,
     );
+    assert_eq!(err.kind, ErrorKind::TypeError);
     assert!(
-        err.contains("filter") && err.contains("must `return`"),
-        "unexpected error: {err}"
+        err.message.contains("must `return`"),
+        "unexpected error: {}",
+        err.message
     );
 }
 
@@ -702,26 +746,1760 @@ fn test_if_and_while_branches_only_evaluate_taken_paths() -> anyhow::Result<()>
 
 #[test]
 fn test_call_target_not_callable_error() {
-    let err = run_and_capture_err(
+    let err = run_and_capture_sludge_err(
         r#"
             let x = 42
             print(x(1,2,3))
         "#,
     );
-    assert!(
-        err.contains("not callable") || err.contains("call target"),
-        "unexpected error: {err}"
-    );
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert_eq!(err.message, "call target is not callable (got type int)");
 }
 
 #[test]
 fn test_conditions_require_bools() {
-    let err = run_and_capture_err(
+    let err = run_and_capture_sludge_err(
         r#"
             if (1) {
                 print("hello world")
             }
         "#,
     );
-    assert!(err.contains("Expcted boolean got: 1"));
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert_eq!(err.message, "Expcted boolean got: 1");
+}
+
+#[test]
+fn test_list_mutations() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let x = list(3, 1, 2)
+            print(x.insert(0, 4)) // 4
+            print(x) // list(4, 3, 1, 2)
+            print(x.remove(1)) // 3
+            print(x.has(1)) // true
+            print(x.has(3)) // false
+            x.reverse()
+            print(x) // list(2, 1, 4)
+            x.sort()
+            print(x) // list(1, 2, 4)
+            x.sort_by(fn(a, b) { return b - a })
+            print(x) // list(4, 2, 1)
+            x.truncate(1)
+            print(x) // list(4)
+        "#,
+    )?;
+
+    let expected = [
+        "4",
+        "list(4, 3, 1, 2)",
+        "3",
+        "true",
+        "false",
+        "list(2, 1, 4)",
+        "list(1, 2, 4)",
+        "list(4, 2, 1)",
+        "list(4)",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_list_transforms() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let x = list(1, 2, 3, 2, 5)
+            print(x.chunks(2)) // list(list(1, 2), list(3, 2), list(5))
+            print(x.chunks(2).flatten()) // list(1, 2, 3, 2, 5)
+            print(x.reduce(fn(acc, item) { return acc + item })) // 13
+            print(x.fold("", fn(acc, item) { return acc + "x" })) // xxxxx
+            print(x.zip(list("a", "b"))) // list(tuple(1, a), tuple(2, b))
+            print(list("a", "b").enumerate()) // list(tuple(0, a), tuple(1, b))
+            print(x.find(fn(item) { return item > 2 })) // 3
+            print(x.find(fn(item) { return item > 10 })) // NULL
+            print(x.index_of(2)) // 1
+            print(x.index_of(42)) // NULL
+            print(x.slice(1, 3)) // list(2, 3)
+            print(x.slice(3)) // list(2, 5)
+            print(x.concat(list(6), list(7))) // list(1, 2, 3, 2, 5, 6, 7)
+            print(x.unique()) // list(1, 2, 3, 5)
+            print(x.min()) // 1
+            print(x.max()) // 5
+            print(x.count(2)) // 2
+        "#,
+    )?;
+
+    let expected = [
+        "list(list(1, 2), list(3, 2), list(5))",
+        "list(1, 2, 3, 2, 5)",
+        "13",
+        "xxxxx",
+        "list(tuple(1, a), tuple(2, b))",
+        "list(tuple(0, a), tuple(1, b))",
+        "3",
+        "NULL",
+        "1",
+        "NULL",
+        "list(2, 3)",
+        "list(2, 5)",
+        "list(1, 2, 3, 2, 5, 6, 7)",
+        "list(1, 2, 3, 5)",
+        "1",
+        "5",
+        "2",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_list_sort_incomparable_error() {
+    let err = run_and_capture_sludge_err(
+        r#"
+            let xs = list(3, "a", 1)
+            xs.sort()
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert_eq!(err.message, "sort: cannot compare 1 with a");
+}
+
+#[test]
+fn test_list_sort_by_stops_at_first_error() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let xs = [5, 3, 8, 1, 9, 2, 7]
+            let calls = 0
+            try {
+                xs.sort_by(fn(a, b) {
+                    calls += 1
+                    if (calls == 3) {
+                        error("boom")
+                    }
+                    return a - b
+                })
+            } catch (e) {
+                print(e.message)
+            }
+            print(calls) // 3
+            print(xs) // unchanged
+
+            // An inconsistent comparator sorts badly but does not fail.
+            let ys = [100 - i for i in range(100)]
+            ys.sort_by(fn(a, b) { return 1 })
+            print(ys.length())
+
+            let stable = [[1, "a"], [0, "b"], [1, "c"], [0, "d"]]
+            stable.sort_by(fn(a, b) { return a.at(0) - b.at(0) })
+            print(stable.map(fn(p) { return p.at(1) }).join(""))
+        "#,
+    )?;
+    assert_eq!(out, "boom\n3\nlist(5, 3, 8, 1, 9, 2, 7)\n100\nbdac\n");
+    Ok(())
+}
+
+#[test]
+fn test_list_callbacks_accept_builtins() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let sink = list()
+            print(list(1, 2, 3).map(sink.push)) // list(1, 2, 3)
+            print(sink) // list(1, 2, 3)
+            print(list(1, 2).map(list)) // list(list(1), list(2))
+            print(list(5, 2).any(sink.has)) // true
+        "#,
+    )?;
+
+    let expected = [
+        "list(1, 2, 3)",
+        "list(1, 2, 3)",
+        "list(list(1), list(2))",
+        "true",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_list_callbacks_receive_index() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let xs = list(10, 20, 30, 40)
+            print(xs.map(fn(item, i) { return item + i })) // list(10, 21, 32, 43)
+            print(xs.filter(fn(item, i) { return i % 2 == 0 })) // list(10, 30)
+            print(xs.all(fn(item, i) { return item > i })) // true
+            print(xs.any(fn(item, i) { return i == 3 })) // true
+        "#,
+    )?;
+
+    let expected = ["list(10, 21, 32, 43)", "list(10, 30)", "true", "true", ""].join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_set_utilities() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let x = set(1, 2, 3)
+            let y = set(3, 4)
+            let z = x.symmetric_difference(y)
+            print(z.length()) // 3
+            print(z.has(3)) // false
+            print(set(1, 2).is_subset(x)) // true
+            print(x.is_subset(y)) // false
+            print(x.is_superset(set(2, 3))) // true
+            print(x.is_disjoint(set(5, 6))) // true
+            print(x.is_disjoint(y)) // false
+            print(x.to_list().sum()) // 6
+            let doubled = x.map(fn(item) { return item * 2 })
+            print(doubled.has(6)) // true
+            print(doubled.has(1)) // false
+            print(x.filter(fn(item) { return item > 1 }).length()) // 2
+        "#,
+    )?;
+
+    let expected = [
+        "3", "false", "true", "false", "true", "true", "false", "6", "true", "false", "2", "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_dict_utilities() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let x = from_items(list(("a", 1), ("b", 2)))
+            print(x.has("a")) // true
+            print(x.has("z")) // false
+            print(x.get_or("z", 42)) // 42
+            print(x.setdefault("c", 3)) // 3
+            print(x.setdefault("c", 4)) // 3
+            let merged = x.merge(dict(("a", 10), ("d", 4)))
+            print(merged.get("a")) // 10
+            print(merged.get("d")) // 4
+            print(x.get("a")) // 1
+            let scaled = x.map_values(fn(value) { return value * 100 })
+            print(scaled.get("b")) // 200
+            let tagged = x.map_values(fn(value, key) { return key + ":" })
+            print(tagged.get("c")) // c:
+            let big = x.filter(fn(value, key) { return value > 1 && key != "c" })
+            print(big.length()) // 1
+            print(big.get("b")) // 2
+        "#,
+    )?;
+
+    let expected = [
+        "true", "false", "42", "3", "3", "10", "4", "1", "200", "c:", "1", "2", "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_collection_literals() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let xs = [1, 2, [3, 4],]
+            print(xs) // list(1, 2, list(3, 4))
+            print(xs.at(2).sum()) // 7
+            print([]) // list()
+            let d = {"a": 1, "b": {"c": true},}
+            print(d.get("a")) // 1
+            print(d.get("b").get("c")) // true
+            print({}.length()) // 0
+            let s = #{1, 2, 2, 3,}
+            print(s.length()) // 3
+            print(#{}.length()) // 0
+            print(null) // NULL
+        "#,
+    )?;
+
+    let expected = [
+        "list(1, 2, list(3, 4))",
+        "7",
+        "list()",
+        "1",
+        "true",
+        "0",
+        "3",
+        "0",
+        "NULL",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_literal_display_round_trips() -> anyhow::Result<()> {
+    let eval = |src: &str| -> anyhow::Result<String> {
+        let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+        let interpreter = Interpreter::new(VariableScope::new(), buffer);
+        let program = parse_program(src)?;
+        let value = interpreter.execute_statement(&program.statements[0])?;
+        Ok(format!("{value:#}"))
+    };
+
+    let src = r#"[1, "two", null, (true, false), {"k": #{"v"}}, []]"#;
+    let printed = eval(src)?;
+    assert_eq!(printed, src);
+    assert_eq!(eval(&printed)?, printed);
+
+    let src = r#"("say \"hi\"\n\tC:\\dir\r",)"#;
+    let printed = eval(src)?;
+    assert_eq!(printed, src);
+    assert_eq!(eval(&printed)?, printed);
+    assert_eq!(eval(r#"{"a\"b": ((1,),)}"#)?, r#"{"a\"b": ((1,),)}"#);
+
+    let out = run_and_capture(r#"print("tab\there", "quote\"", "keep\d")"#)?;
+    assert_eq!(out, "tab\there quote\" keep\\d\n");
+    Ok(())
+}
+
+#[test]
+fn test_dict_literal_unhashable_key_error() {
+    let err = run_and_capture_error(
+        r#"
+            let d = {[1]: 2}
+        "#,
+    );
+    assert_eq!(
+        SludgeError::find(&err).map(|e| e.kind),
+        Some(ErrorKind::TypeError)
+    );
+    assert!(
+        err.to_string().contains("dict literal") && err.to_string().contains("not hashable"),
+        "unexpected error: {err}"
+    );
+}
+
+#[test]
+fn test_comprehensions() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let xs = [-1, 2, 3, -4]
+            print([x * 2 for x in xs if x > 0]) // list(4, 6)
+            print([x for x in #{5}]) // list(5)
+
+            let d = {"a": 1, "b": 2}
+            let swapped = {v: k for (k, v) in d.items()}
+            print(swapped.get(1)) // a
+            print(swapped.get(2)) // b
+
+            let signs = #{x > 0 for x in xs}
+            print(signs.length()) // 2
+            print([k for k in d if k == "a"]) // list(a)
+        "#,
+    )?;
+
+    let expected = ["list(4, 6)", "list(5)", "a", "b", "2", "list(a)", ""].join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_comprehension_closures_capture_iteration_binding() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let fns = [fn() { return x * 10 } for x in [1, 2, 3]]
+            print(fns.map(fn(f) { return f() })) // list(10, 20, 30)
+        "#,
+    )?;
+
+    assert_eq!(out, ["list(10, 20, 30)", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_comprehension_errors() {
+    let err = run_and_capture_sludge_err(
+        r#"
+            print([x for x in 42])
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert_eq!(err.message, "type 'int' is not iterable");
+
+    let err = run_and_capture_sludge_err(
+        r#"
+            print([a for (a, b) in [(1, 2, 3)]])
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::ValueError);
+    assert_eq!(err.message, "cannot unpack tuple of length 3 into 2 names");
+}
+
+#[test]
+fn test_lazy_iterators() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            print(range(5).collect()) // list(0, 1, 2, 3, 4)
+            print(range(2, 10, 3).collect()) // list(2, 5, 8)
+            print(range(3, 0, -1).collect()) // list(3, 2, 1)
+
+            // Only the elements that are pulled through the chain are evaluated
+            let seen = list()
+            let squares = range(1000000000).map(fn(x) {
+                seen.push(x)
+                return x * x
+            })
+            print(squares.filter(fn(x) { return x % 2 == 1 }).take(3).collect()) // list(1, 9, 25)
+            print(seen.length()) // 6
+
+            print(range(10).skip(7).collect()) // list(7, 8, 9)
+            print(range(10).take_while(fn(x) { return x < 3 }).collect()) // list(0, 1, 2)
+            print(range(2).chain([7, 8]).collect()) // list(0, 1, 7, 8)
+
+            let it = [1, 2].iter()
+            print(it.next()) // 1
+            print(it.next()) // 2
+            print(it.next()) // NULL
+            print([x * 10 for x in range(3)]) // list(0, 10, 20)
+        "#,
+    )?;
+
+    let expected = [
+        "list(0, 1, 2, 3, 4)",
+        "list(2, 5, 8)",
+        "list(3, 2, 1)",
+        "list(1, 9, 25)",
+        "6",
+        "list(7, 8, 9)",
+        "list(0, 1, 2)",
+        "list(0, 1, 7, 8)",
+        "1",
+        "2",
+        "NULL",
+        "list(0, 10, 20)",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_generators() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let naturals = fn() {
+                let n = 0
+                while (true) {
+                    yield n
+                    n = n + 1
+                }
+                return null
+            }
+            print(naturals().take(4).collect()) // list(0, 1, 2, 3)
+
+            let countdown = fn(from) {
+                print("start")
+                for (let i = from; i > 0; i = i - 1) {
+                    if (i % 2 == 0) {
+                        yield i * 100
+                    } else {
+                        yield i
+                    }
+                }
+                yield "liftoff"
+                return null
+            }
+
+            let gen = countdown(3)
+            print("created")
+            print(gen.next()) // start, 3
+            print(gen.next()) // 200
+            print(gen.collect()) // list(1, liftoff)
+            print(gen.next()) // NULL
+
+            let early = fn() {
+                yield 1
+                return null
+                yield 2
+            }
+            print(early().collect()) // list(1)
+        "#,
+    )?;
+
+    let expected = [
+        "list(0, 1, 2, 3)",
+        "created",
+        "start",
+        "3",
+        "200",
+        "list(1, liftoff)",
+        "NULL",
+        "list(1)",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_yield_prefixed_identifiers_do_not_make_generators() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let collect_twice = fn(x) {
+                let yielded = []
+                yielded.push(x)
+                yielded.push(x)
+                return yielded
+            }
+            print(collect_twice(1)) // list(1, 1)
+        "#,
+    )?;
+    assert_eq!(out, ["list(1, 1)", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_yield_inside_try_is_rejected_when_created() {
+    let err = run_and_capture_sludge_err(
+        r#"
+            let gen = fn() {
+                print("started")
+                while (true) {
+                    try {
+                        yield 1
+                    } catch (e) {
+                        print(e)
+                    }
+                }
+            }
+            gen()
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::RuntimeError);
+    assert_eq!(err.message, "`yield` is not supported inside `try`");
+
+    let err = run_and_capture_sludge_err(
+        r#"
+            let gen = fn() {
+                try {
+                    error("boom")
+                } catch (e) {
+                    yield e.message
+                }
+            }
+            gen()
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::RuntimeError);
+
+    // A `try` in a nested function is fine.
+
+    let out = run_and_capture(
+        r#"
+            let gen = fn() {
+                let safe = fn() {
+                    try {
+                        return 1 / 0
+                    } catch (e) {
+                        return e.kind
+                    }
+                }
+                yield safe()
+            }
+            print(gen().collect())
+        "#,
+    );
+    assert_eq!(out.ok().as_deref(), Some("list(DivisionByZero)\n"));
+}
+
+#[test]
+fn test_yield_outside_function_error() {
+    let err = run_and_capture_sludge_err(
+        r#"
+            yield 1
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::RuntimeError);
+    assert_eq!(err.message, "`yield` is only allowed inside a function");
+}
+
+#[test]
+fn test_reentrant_iterator_error() {
+    let err = run_and_capture_sludge_err(
+        r#"
+            let it = null
+            let gen = fn() {
+                yield it.next()
+            }
+            it = gen()
+            it.next()
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::RuntimeError);
+    assert_eq!(err.message, "next: iterator is already running");
+}
+
+#[test]
+fn test_sys_args_and_env() -> anyhow::Result<()> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let variables = VariableScope::new();
+    variables.declare(
+        "args".to_string(),
+        builtins::sys::args_builtin(vec!["a".to_string(), "b".to_string()]),
+    );
+    let program = parse_program(
+        r#"
+            print(args()) // list(a, b)
+            args().push("c")
+            print(args().length()) // 2
+            print(env("SLUDGE_SURELY_UNSET_VARIABLE")) // NULL
+            print(env("PATH") != null) // true
+        "#,
+    )?;
+    Interpreter::new(variables, buffer.clone()).run_program(&program)?;
+
+    let out = String::from_utf8(buffer.borrow().to_vec())?;
+    assert_eq!(out, ["list(a, b)", "2", "NULL", "true", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_sys_exit_unwinds_with_code() -> anyhow::Result<()> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let program = parse_program(
+        r#"
+            let f = fn() {
+                if (true) {
+                    exit(7)
+                }
+                return 1
+            }
+            print("before")
+            list(1).map(fn(x) { return f() })
+            print("after")
+        "#,
+    )?;
+    let err = Interpreter::new(VariableScope::new(), buffer.clone())
+        .run_program(&program)
+        .expect_err("exit should unwind the program");
+
+    assert_eq!(builtins::sys::Exit::find(&err).map(|e| e.code), Some(7));
+    assert_eq!(String::from_utf8(buffer.borrow().to_vec())?, "before\n");
+    Ok(())
+}
+
+fn run_with_fs(src: &str, read: &[PathBuf], write: &[PathBuf]) -> anyhow::Result<String> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let variables = VariableScope::new();
+    builtins::fs::register(&variables, builtins::fs::Permissions::new(read, write)?);
+    let program = parse_program(src)?;
+    Interpreter::new(variables, buffer.clone()).run_program(&program)?;
+    Ok(String::from_utf8(buffer.borrow().to_vec())?)
+}
+
+#[test]
+fn test_fs_builtins_within_granted_roots() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join(format!("sludge-fs-{}", std::process::id()));
+    std::fs::create_dir_all(&root)?;
+    let dir = root.display().to_string();
+
+    let out = run_with_fs(
+        &format!(
+            r#"
+                let dir = "{dir}"
+                mkdir(dir + "/out")
+                write_file(dir + "/out/a.txt", "hello")
+                append_file(dir + "/out/a.txt", " world")
+                print(read_file(dir + "/out/a.txt"))
+                print(exists(dir + "/out/a.txt"))
+                print(list_dir(dir + "/out"))
+                remove_file(dir + "/out/a.txt")
+                print(exists(dir + "/out/a.txt"))
+            "#
+        ),
+        std::slice::from_ref(&root),
+        std::slice::from_ref(&root),
+    );
+    std::fs::remove_dir_all(&root)?;
+
+    assert_eq!(
+        out?,
+        ["hello world", "true", "list(a.txt)", "false", ""].join("\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fs_builtins_deny_ungranted_paths() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join(format!("sludge-fs-deny-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("data"))?;
+    let dir = root.display().to_string();
+    let data = [root.join("data")];
+
+    let denied_write = run_with_fs(
+        &format!(r#"write_file("{dir}/data/x.txt", "nope")"#),
+        &data,
+        &[],
+    );
+    let escaped_read = run_with_fs(&format!(r#"read_file("{dir}/data/../secret")"#), &data, &[]);
+    let default_denied = run_and_capture(&format!(r#"exists("{dir}")"#));
+    std::fs::remove_dir_all(&root)?;
+
+    for result in [denied_write, escaped_read, default_denied] {
+        let err = result.expect_err("access should be denied");
+        let error = SludgeError::find(&err).expect("a typed error");
+        assert_eq!(error.kind, ErrorKind::PermissionError);
+        assert!(
+            error.message.contains("permission denied"),
+            "unexpected error: {err}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_fs_builtins_follow_dangling_symlinks() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join(format!("sludge-fs-link-{}", std::process::id()));
+    let outside = root.join("outside");
+    std::fs::create_dir_all(root.join("out"))?;
+    std::fs::create_dir_all(&outside)?;
+    std::os::unix::fs::symlink(outside.join("evil_target"), root.join("out/link"))?;
+    std::os::unix::fs::symlink(root.join("out/inside"), root.join("out/inner"))?;
+    let dir = root.display().to_string();
+    let out = [root.join("out")];
+
+    let escaped = run_with_fs(
+        &format!(r#"write_file("{dir}/out/link", "pwned")"#),
+        &[],
+        &out,
+    );
+    let inner = run_with_fs(
+        &format!(r#"write_file("{dir}/out/inner", "ok")"#),
+        &[],
+        &out,
+    );
+    let created = outside.join("evil_target").exists();
+    let written = std::fs::read_to_string(root.join("out/inside"));
+    std::fs::remove_dir_all(&root)?;
+
+    let err = escaped.expect_err("the link points outside the granted root");
+    assert_eq!(
+        SludgeError::find(&err).map(|e| e.kind),
+        Some(ErrorKind::PermissionError)
+    );
+    assert!(!created);
+    inner?;
+    assert_eq!(written?, "ok");
+    Ok(())
+}
+
+fn run_with_process(src: &str) -> anyhow::Result<String> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let variables = VariableScope::new();
+    builtins::process::register(&variables, true);
+    let program = parse_program(src)?;
+    Interpreter::new(variables, buffer.clone()).run_program(&program)?;
+    Ok(String::from_utf8(buffer.borrow().to_vec())?)
+}
+
+#[test]
+fn test_exec_captures_output() -> anyhow::Result<()> {
+    let out = run_with_process(
+        r#"
+            let result = exec("sh", ["-c", "echo out; echo err >&2; exit 3"])
+            print(result.get("status")) // 3
+            print(result.get("stdout")) // out
+            print(result.get("stderr")) // err
+
+            let piped = exec("cat", [], {"stdin": "piped input"})
+            print(piped.get("stdout")) // piped input
+
+            let opts = {"cwd": "/", "env": {"SLUDGE_GREETING": "hi"}}
+            print(exec("sh", ["-c", "pwd; echo $SLUDGE_GREETING"], opts).get("stdout"))
+        "#,
+    )?;
+
+    assert_eq!(out, "3\nout\n\nerr\n\npiped input\n/\nhi\n\n");
+    Ok(())
+}
+
+#[test]
+fn test_exec_pipes_large_stdin() -> anyhow::Result<()> {
+    let out = run_with_process(
+        r#"
+            let input = "0123456789abcdef"
+            for (let i = 0; i < 14; i += 1) {
+                input = input + input
+            }
+            let result = exec("cat", [], {"stdin": input})
+            print(result.get("stdout") == input)
+            print(exec("true", [], {"stdin": input}).get("status"))
+        "#,
+    )?;
+    assert_eq!(out, "true\n0\n");
+    Ok(())
+}
+
+#[test]
+fn test_spawn_returns_a_process_handle() -> anyhow::Result<()> {
+    let out = run_with_process(
+        r#"
+            let p = spawn("sh", ["-c", "exit $(cat)"], {"stdin": "4"})
+            print(p.pid > 0)
+            print(p.wait())
+            print(p.wait())
+
+            let sleeper = spawn("sleep", ["10"])
+            sleeper.kill()
+            print(sleeper.wait())
+        "#,
+    )?;
+    assert_eq!(out, "true\n4\n4\nNULL\n");
+    Ok(())
+}
+
+#[test]
+fn test_exec_requires_allow_run() {
+    let err = run_and_capture_sludge_err(
+        r#"
+            exec("echo", ["hi"])
+        "#,
+    );
+    assert_eq!(err.kind, ErrorKind::PermissionError);
+    assert!(
+        err.message.contains("permission denied") && err.message.contains("--allow-run"),
+        "unexpected error: {}",
+        err.message
+    );
+}
+
+#[test]
+fn test_json_parse_and_stringify() -> anyhow::Result<()> {
+    let doc = builtins::json::parse(
+        &Value::Null,
+        &[Value::String(
+            r#"{"name": "sludge", "tags": [1, true, null], "nested": {}}"#.to_string(),
+        )],
+    )?;
+
+    let variables = VariableScope::new();
+    variables.declare("doc".to_string(), doc);
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let program = parse_program(
+        r#"
+            print(doc.get("name")) // sludge
+            print(doc.get("tags")) // list(1, true, NULL)
+            print(doc.get("nested").length()) // 0
+            print(json.parse("[2.0, 1e3, -0.0]")) // list(2, 1000, 0)
+            print(json.parse("[1.5, -2.7, 3]", floats: "truncate")) // list(1, -2, 3)
+            print(json.parse("[[1.25]]", "string").at(0).at(0) == "1.25") // true
+            print(json.stringify({"b": [1, 2], "a": null})) // {"a":null,"b":[1,2]}
+            print(json.stringify((1, "x"))) // [1,"x"]
+            print(json.stringify([1], true))
+            print(json.stringify(json.parse(json.stringify(doc))) == json.stringify(doc)) // true
+        "#,
+    )?;
+    Interpreter::new(variables, buffer.clone()).run_program(&program)?;
+
+    let out = String::from_utf8(buffer.borrow().to_vec())?;
+    assert_eq!(
+        out,
+        [
+            "sludge",
+            "list(1, true, NULL)",
+            "0",
+            "list(2, 1000, 0)",
+            "list(1, -2, 3)",
+            "true",
+            r#"{"a":null,"b":[1,2]}"#,
+            r#"[1,"x"]"#,
+            "[\n  1\n]",
+            "true",
+            "",
+        ]
+        .join("\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_json_errors() {
+    let parse = |text: &str| {
+        builtins::json::parse(&Value::Null, &[Value::String(text.to_string())])
+            .unwrap_err()
+            .to_string()
+    };
+
+    let err = parse("{\n  \"a\": }");
+    assert!(
+        err.contains("json.parse") && err.contains("line 2 column 8"),
+        "unexpected error: {err}"
+    );
+
+    let err = builtins::json::parse(&Value::Null, &[Value::String("1.5".to_string())]).unwrap_err();
+    let error = SludgeError::find(&err).expect("a typed error");
+    assert_eq!(error.kind, ErrorKind::ValueError);
+    assert_eq!(
+        error.message,
+        "json.parse: number 1.5 is not an integer; floats are not supported"
+    );
+
+    let err = run_and_capture_sludge_err(r#"json.parse("1.5", floats: "round")"#);
+    assert_eq!(err.kind, ErrorKind::ValueError);
+    assert!(err.message.contains("floats must be"), "{}", err.message);
+
+    let err = parse("1e10");
+    assert!(
+        err.contains("does not fit in an int"),
+        "unexpected error: {err}"
+    );
+
+    let err = run_and_capture_err(r#"json.stringify({1: 2})"#);
+    assert!(
+        err.contains("json.stringify") && err.contains("not a string"),
+        "unexpected error: {err}"
+    );
+
+    let err = run_and_capture_err(r#"json.stringify([fn() { return 1 }])"#);
+    assert!(
+        err.contains("functions cannot be converted"),
+        "unexpected error: {err}"
+    );
+}
+
+#[test]
+fn test_value_serde_round_trip() -> anyhow::Result<()> {
+    let variables = VariableScope::new();
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let program = parse_program(
+        r#"
+            let snapshot = {"ids": #{3, 1, 2}, "pair": (1, "a"), "none": null}
+        "#,
+    )?;
+    Interpreter::new(variables.clone(), buffer).run_program(&program)?;
+    let snapshot = variables.get("snapshot").expect("snapshot is declared");
+
+    let json = serde_json::to_value(&snapshot)?;
+    assert_eq!(
+        json,
+        serde_json::json!({"ids": [1, 2, 3], "pair": [1, "a"], "none": null})
+    );
+
+    let restored: Value = serde_json::from_value(json.clone())?;
+    assert_eq!(serde_json::to_value(&restored)?, json);
+    assert_eq!(Value::from_json(json)?.to_json()?, snapshot.to_json()?);
+
+    let ints = Value::from_json(serde_json::json!([1, 2]))?;
+    assert_eq!(format!("{ints:#}"), "[1, 2]");
+
+    let iter = builtins::iterator::range(&Value::Null, &[Value::Int32(3)])?;
+    let err = serde_json::to_string(&iter).unwrap_err();
+    assert!(
+        err.to_string().contains("cannot be serialized"),
+        "unexpected error: {err}"
+    );
+    Ok(())
+}
+
+fn run_with_limits(src: &str, limits: Limits) -> anyhow::Result<Value> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let program = parse_program(src)?;
+    Interpreter::with_limits(VariableScope::new(), buffer, limits).run_program(&program)
+}
+
+#[test]
+fn test_call_depth_limit() -> anyhow::Result<()> {
+    let deep = r#"
+        let depth = fn(n) {
+            if (n == 0) {
+                return 0
+            }
+            return 1 + depth(n - 1)
+        }
+        print(depth(900))
+    "#;
+    run_with_limits(deep, Limits::default())?;
+
+    let err = run_with_limits(
+        "let forever = fn(n) { return 1 + forever(n + 1) }\nforever(0)",
+        Limits::default(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::StackOverflow {
+            depth: Limits::DEFAULT_MAX_CALL_DEPTH
+        })
+    );
+    assert_eq!(err.to_string(), "stack overflow: call depth exceeded 1000");
+    Ok(())
+}
+
+#[test]
+fn test_step_time_and_size_limits() {
+    let err = run_with_limits(
+        "while (true) {}",
+        Limits {
+            max_steps: Some(10_000),
+            ..Limits::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::StepLimit { steps: 10_000 })
+    );
+
+    let duration = std::time::Duration::from_millis(20);
+    let err = run_with_limits(
+        "while (true) {}",
+        Limits {
+            max_duration: Some(duration),
+            ..Limits::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::Timeout { duration })
+    );
+
+    let err = run_with_limits(
+        r#"
+            let xs = []
+            while (true) {
+                xs.push(1)
+            }
+        "#,
+        Limits {
+            max_collection_size: Some(100),
+            ..Limits::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::CollectionTooLarge {
+            size: 101,
+            limit: 100
+        })
+    );
+}
+
+#[test]
+fn test_limits_apply_inside_builtin_loops() {
+    let limits = Limits {
+        max_steps: Some(100_000),
+        max_duration: Some(std::time::Duration::from_secs(2)),
+        max_collection_size: Some(1000),
+        ..Limits::default()
+    };
+    let err = run_with_limits("range(300000000).collect()", limits.clone()).unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::CollectionTooLarge {
+            size: 1001,
+            limit: 1000
+        })
+    );
+
+    let err = run_with_limits("[x for x in range(300000000)]", limits.clone()).unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::CollectionTooLarge {
+            size: 1001,
+            limit: 1000
+        })
+    );
+
+    let skip = "range(2000000000).skip(1999999999).next()";
+    let err = run_with_limits(skip, limits).unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::StepLimit { steps: 100_000 })
+    );
+
+    let duration = std::time::Duration::from_millis(20);
+    let err = run_with_limits(
+        skip,
+        Limits {
+            max_duration: Some(duration),
+            ..Limits::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::Timeout { duration })
+    );
+}
+
+#[test]
+fn test_cancellation_aborts_run_and_keeps_state() -> anyhow::Result<()> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let interpreter = Interpreter::new(VariableScope::new(), buffer.clone());
+    interpreter.run_program(&parse_program("let x = 41")?)?;
+
+    let handle = interpreter.cancellation_handle();
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        handle.cancel();
+    });
+    let err = interpreter
+        .run_program(&parse_program("while (true) { x = x }")?)
+        .unwrap_err();
+    canceller.join().expect("canceller thread panicked");
+    assert_eq!(LimitError::find(&err), Some(&LimitError::Interrupted));
+    assert!(!interpreter.cancellation_handle().is_cancelled());
+
+    interpreter.run_program(&parse_program("print(x + 1)")?)?;
+    assert_eq!(String::from_utf8(buffer.borrow().to_vec())?, "42\n");
+    Ok(())
+}
+
+#[test]
+fn test_cancellation_stops_builtin_loops() -> anyhow::Result<()> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let interpreter = Interpreter::new(VariableScope::new(), buffer);
+
+    let handle = interpreter.cancellation_handle();
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        handle.cancel();
+    });
+    let err = interpreter
+        .run_program(&parse_program("range(2000000000).skip(1999999999).next()")?)
+        .unwrap_err();
+    canceller.join().expect("canceller thread panicked");
+    assert_eq!(LimitError::find(&err), Some(&LimitError::Interrupted));
+    Ok(())
+}
+
+#[test]
+fn test_tail_calls_run_in_constant_stack() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let count = fn(n, acc) {
+                if (n == 0) {
+                    return acc
+                }
+                return count(n - 1, acc + 1)
+            }
+            print(count(1000000, 0)) // 1000000
+
+            let is_even = fn(n) {
+                if (n == 0) {
+                    return true
+                }
+                return is_odd(n - 1)
+            }
+            let is_odd = fn(n) {
+                if (n == 0) {
+                    return false
+                }
+                return is_even(n - 1)
+            }
+            print(is_even(100001)) // false
+
+            let last = fn(xs, i) {
+                if (i == xs.length() - 1) {
+                    return xs.at(i)
+                }
+                return last(xs, i + 1)
+            }
+            print(last([1, 2, 3], 0)) // 3
+        "#,
+    )?;
+    assert_eq!(out, ["1000000", "false", "3", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_runtime_error_kinds() {
+    let cases = [
+        ("print(does_not_exist)", ErrorKind::NameError),
+        ("[1, 2].whoopsies()", ErrorKind::NameError),
+        ("print(1 - \"a\")", ErrorKind::TypeError),
+        ("if (1) { print(1) }", ErrorKind::TypeError),
+        ("list(1).at(5)", ErrorKind::IndexError),
+        ("list().pop()", ErrorKind::IndexError),
+        ("let f = fn(a) { return a }\nf(1, 2)", ErrorKind::ArityError),
+        ("list(1).at()", ErrorKind::ArityError),
+        ("range(1, 5, 0)", ErrorKind::ValueError),
+        ("print(1 / 0)", ErrorKind::DivisionByZero),
+        ("print(7 % 0)", ErrorKind::DivisionByZero),
+        ("print(2147483647 + 1)", ErrorKind::Overflow),
+        ("print(2 ^ 40)", ErrorKind::Overflow),
+    ];
+    for (src, kind) in cases {
+        assert_eq!(run_and_capture_sludge_err(src).kind, kind, "for {src}");
+    }
+
+    // Messages name types rather than leaking Rust debug output.
+    let err = run_and_capture_sludge_err(r#"print(1 - "a")"#);
+    assert_eq!(
+        err.message,
+        "Subtraction not supported between int and string"
+    );
+}
+
+#[test]
+fn test_runtime_errors_carry_span_and_trace() {
+    let err = run_and_capture_sludge_err(
+        "let inner = fn(x) {
+    return x / 0
+}
+let outer = fn(x) {
+    return 1 + inner(x)
+}
+print(outer(5))",
+    );
+    assert_eq!(err.kind, ErrorKind::DivisionByZero);
+    assert_eq!(
+        err.span,
+        Some(Span {
+            line: 2,
+            column: 14
+        })
+    );
+    let trace: Vec<_> = err
+        .trace
+        .iter()
+        .map(|frame| (frame.function.as_str(), frame.span))
+        .collect();
+    assert_eq!(
+        trace,
+        [
+            (
+                "inner",
+                Span {
+                    line: 5,
+                    column: 16
+                }
+            ),
+            ("outer", Span { line: 7, column: 7 }),
+        ]
+    );
+}
+
+#[test]
+fn test_try_catch_finally() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+let safe_div = fn(a, b) {
+    try {
+        return a / b
+    } catch (e) {
+        print(e.kind, e.message, e.location)
+        return null
+    } finally {
+        print("done")
+    }
+}
+print(safe_div(6, 3))
+print(safe_div(1, 0))
+
+try {
+    list().pop()
+} catch (e) {
+    print(e.kind)
+}
+
+try {
+    throw "bad input"
+} catch (e) {
+    print(e, e.location)
+}
+
+try {
+    error("from error()")
+} catch (e) {
+    print(e.kind, e.message)
+}
+
+let outer = 0
+try {
+    try {
+        print(missing)
+    } catch (e) {
+        throw e
+    } finally {
+        outer = 1
+    }
+} catch (e) {
+    print(e.kind, outer)
+}
+"#,
+    )?;
+    assert_eq!(
+        out,
+        [
+            "done",
+            "2",
+            "DivisionByZero Division by zero 4:18",
+            "done",
+            "NULL",
+            "IndexError",
+            "UserError: bad input 22:5",
+            "UserError from error()",
+            "NameError 1",
+            "",
+        ]
+        .join("\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_identifiers_may_start_with_throw() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let throwaway = [1]
+            throwaway.push(2)
+            print(throwaway) // list(1, 2)
+            try {
+                throw(throwaway.length())
+            } catch (e) {
+                print(e) // UserError: 2
+            }
+        "#,
+    )?;
+    assert_eq!(out, ["list(1, 2)", "UserError: 2", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_uncaught_and_uncatchable_errors() -> anyhow::Result<()> {
+    let err = run_and_capture_sludge_err(r#"throw "oops""#);
+    assert_eq!(err.kind, ErrorKind::UserError);
+    assert_eq!(err.message, "oops");
+
+    // Exceeding the call depth is catchable, since the stack has unwound
+    // by the time the handler runs.
+    let out = run_and_capture(
+        r#"
+            let forever = fn(n) { return 1 + forever(n + 1) }
+            try {
+                forever(0)
+            } catch (e) {
+                print(e.kind)
+            }
+        "#,
+    )?;
+    assert_eq!(out, "StackOverflow\n");
+
+    // Other limits abort the run.
+    let err = run_with_limits(
+        r#"
+            try {
+                while (true) {}
+            } catch (e) {
+                print("caught")
+            }
+        "#,
+        Limits {
+            max_steps: Some(1000),
+            ..Limits::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        LimitError::find(&err),
+        Some(&LimitError::StepLimit { steps: 1000 })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_traceback_lists_calls_innermost_first() {
+    let err = run_and_capture_sludge_err(
+        "let factorial = fn(n) {
+    if (n == 0) {
+        return [].at(n)
+    }
+    return n * factorial(n - 1)
+}
+print(factorial(2))",
+    );
+    assert_eq!(err.kind, ErrorKind::IndexError);
+    assert_eq!(
+        err.traceback("main.sludge"),
+        [
+            "  at factorial (main.sludge:3:19)",
+            "  at factorial (main.sludge:5:16)",
+            "  at factorial (main.sludge:5:16)",
+            "  at main.sludge:7:7",
+        ]
+        .join("\n")
+    );
+
+    // A failing call is reported at its call site, without a frame.
+    let err = run_and_capture_sludge_err("let f = fn(a) { return a }\nf(1, 2)");
+    assert_eq!(err.kind, ErrorKind::ArityError);
+    assert_eq!(err.traceback("main.sludge"), "  at main.sludge:2:1");
+}
+
+#[test]
+fn test_function_declarations_are_hoisted() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            print(is_even(10), is_odd(7))
+
+            fn is_even(n) {
+                if (n == 0) {
+                    return true
+                }
+                return is_odd(n - 1)
+            }
+
+            fn is_odd(n) {
+                if (n == 0) {
+                    return false
+                }
+                return is_even(n - 1)
+            }
+
+            let outer = fn() {
+                return helper(2)
+                fn helper(x) {
+                    return x * 21
+                }
+            }
+            print(outer())
+
+            let numbers = fn() {
+                fn double(x) {
+                    return x * 2
+                }
+                yield double(1)
+                yield double(2)
+            }
+            print(numbers().collect())
+        "#,
+    )?;
+    assert_eq!(out, ["true true", "42", "list(2, 4)", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_function_declarations_run_as_no_ops() -> anyhow::Result<()> {
+    let buffer: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let interpreter = Interpreter::new(VariableScope::new(), buffer.clone());
+
+    // A hoisted declaration is not declared again when reached, so it does
+    // not undo a later assignment.
+    interpreter.run_program(&parse_program(
+        r#"
+            print(f)
+            f = 2
+            fn f() {
+                return 1
+            }
+            print(f)
+        "#,
+    )?)?;
+
+    // Statements run one at a time, as in the REPL, need hoisting first.
+    let program = parse_program("fn g() { return 3 }")?;
+    interpreter.execute_statement(&program.statements[0])?;
+    assert!(interpreter.variables.get("g").is_none());
+    interpreter.hoist(&program.statements)?;
+    interpreter.run_program(&parse_program("print(g())")?)?;
+
+    let out = String::from_utf8(buffer.borrow().to_vec())?;
+    assert_eq!(out, ["fn f()", "2", "3", ""].join("\n"));
+    Ok(())
+}
+
+#[test]
+fn test_functions_display_and_trace_their_names() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            fn add(a, b) {
+                return a + b
+            }
+            let square = fn(x) { return x * x }
+            let alias = add
+            print(add, square, alias, fn(y) { return y })
+        "#,
+    )?;
+    assert_eq!(
+        out,
+        "fn add(a, b) fn square(x) fn add(a, b) fn <anonymous>(y)\n"
+    );
+
+    let err = run_and_capture_sludge_err(
+        "fn fail(x) {
+    return x / 0
+}
+let alias = fail
+print(alias(1) + 1)",
+    );
+    assert_eq!(
+        err.traceback("main.sludge"),
+        ["  at fail (main.sludge:2:14)", "  at main.sludge:5:7"].join("\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_default_rest_and_named_parameters() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            fn point(x, y = x * 2, label = "p") {
+                return (label, x, y)
+            }
+            print(point(1))
+            print(point(1, 5))
+            print(point(y: 3, x: 4))
+            print(point(1, label: "q"))
+
+            fn split(first, ...rest) {
+                return (first, rest)
+            }
+            print(split(1), split(1, 2, 3))
+            print(split)
+            print(range(1, 10, step: 4).collect(), range(2, step: 1).collect())
+            print(json.stringify(null), json.stringify(pretty: false, value: [1]))
+        "#,
+    )?;
+    assert_eq!(
+        out,
+        "tuple(p, 1, 2)\ntuple(p, 1, 5)\ntuple(p, 4, 3)\ntuple(q, 1, 2)\ntuple(1, list()) tuple(1, list(2, 3))\nfn split(first, ...rest)\nlist(1, 5, 9) list(0, 1)\nnull [1]\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_arity_errors_show_the_signature() {
+    let cases = [
+        (
+            "fn add(a, b) { return a + b }\nadd(1, 2, 3)",
+            "add(a, b): expected 2 argument(s), got 3",
+        ),
+        (
+            "fn add(a, b = 1) { return a + b }\nadd(b: 2)",
+            "add(a, b?): missing argument 'a'",
+        ),
+        (
+            "fn add(a, b = 1) { return a + b }\nadd(1, 2, 3)",
+            "add(a, b?): expected at most 2 argument(s), got 3",
+        ),
+        (
+            "fn add(a, b) { return a + b }\nadd(1, c: 2)",
+            "add(a, b): unknown argument 'c'",
+        ),
+        (
+            "fn add(a, b) { return a + b }\nadd(1, a: 2)",
+            "add(a, b): argument 'a' given more than once",
+        ),
+        (
+            "let f = fn(x) { return x }\nf()",
+            "f(x): missing argument 'x'",
+        ),
+        ("fn(x) { return x }()", "fn(x): missing argument 'x'"),
+        (
+            "range(1, 2, 3, 4)",
+            "range(start, end?, step?): expected at most 3 argument(s), got 4",
+        ),
+        ("error()", "error(message): missing argument 'message'"),
+        ("[1].map(fn: 1)", "unexpected named argument 'fn'"),
+    ];
+    for (src, expected) in cases {
+        let err = run_and_capture_sludge_err(src);
+        assert_eq!(err.kind, ErrorKind::ArityError, "{src}");
+        assert_eq!(err.message, expected, "{src}");
+    }
+}
+
+#[test]
+fn test_invalid_parameter_lists_are_parse_errors() {
+    for src in [
+        "fn f(...rest, x) { return x }",
+        "fn f(...rest = 1) { return rest }",
+        "f(x: 1, 2)",
+    ] {
+        assert!(parse_program(src).is_err(), "{src}");
+    }
+}
+
+#[test]
+fn test_pipe_compose_and_partial() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            fn double(x) { return x * 2 }
+            fn add(a, b) { return a + b }
+            let inc = partial(add, 1)
+            print(3 |> double |> add(10))
+            print([1, 2, 3] |> fn(xs) { return xs.map(double) })
+            print(1 + 2 |> double, 2 |> add(b: 5))
+            print(compose(double, inc)(4), compose(inc, add)(1, 2))
+            print([1, 2].map(inc), inc(b: 9), partial(add)(2, 3))
+            let upto = partial(range, 0)
+            print(5 |> upto |> fn(it) { return it.collect() })
+        "#,
+    )?;
+    assert_eq!(
+        out,
+        "16\nlist(2, 4, 6)\n6 7\n10 4\nlist(2, 3) 10 5\nlist(0, 1, 2, 3, 4)\n"
+    );
+
+    let err = run_and_capture_sludge_err("fn add(a, b) { return a + b }\npartial(add, 1, 2)(3)");
+    assert_eq!(err.kind, ErrorKind::ArityError);
+    assert_eq!(err.message, "add(a, b): expected 2 argument(s), got 3");
+
+    let err = run_and_capture_sludge_err("compose(1, 2)");
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert_eq!(err.message, "compose: argument must be a function, got 1");
+    Ok(())
+}
+
+#[test]
+fn test_compound_assignment_and_new_operators() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let x = 10
+            x += 5
+            x -= 3
+            x *= 4
+            x /= 6
+            x %= 5
+            let s = "ab"
+            s += "c"
+            print(x, s)
+
+            let total = 0
+            for (let i = 0; i < 4; i += 1) {
+                total += i
+            }
+            print(total)
+
+            print(6 & 3, 6 | 3, 6 xor 3, 1 << 4, -16 >> 2, 2 ** 10, 2 ** 3 ** 2)
+            print(1 | 2 == 3, 1 + 2 << 1)
+
+            print(2 in [1, 2], 3 in (1, 2), "a" in {"a": 1}, 4 in #{4}, "ell" in "hello")
+            print(!(5 in range(3).collect()), [1, 2] in [[1, 2]])
+
+            let sign = fn(n) { return n < 0 ? "neg" : n == 0 ? "zero" : "pos" }
+            print(sign(-2), sign(0), sign(7), true ? 1 : 2 + 3, false ? 1 : 2 + 3)
+            print([n > 1 ? n : 0 for n in [1, 2, 3]])
+        "#,
+    )?;
+    assert_eq!(
+        out,
+        "3 abc\n6\n2 7 5 16 -4 1024 512\ntrue 6\ntrue false true true true\ntrue false\nneg zero pos 1 5\nlist(0, 2, 3)\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_new_operator_errors() {
+    let cases = [
+        (
+            "1 & true",
+            ErrorKind::TypeError,
+            "Bitwise and not supported between int and boolean",
+        ),
+        (
+            "1 << 32",
+            ErrorKind::ValueError,
+            "shift amount out of range in 1 << 32",
+        ),
+        (
+            "1 >> -1",
+            ErrorKind::ValueError,
+            "shift amount out of range in 1 >> -1",
+        ),
+        (
+            "1 in 2",
+            ErrorKind::TypeError,
+            "'in' not supported for type int",
+        ),
+        (
+            "1 in \"abc\"",
+            ErrorKind::TypeError,
+            "'in <string>' requires a string, got int",
+        ),
+        ("1 ? 2 : 3", ErrorKind::TypeError, "Expcted boolean got: 1"),
+        (
+            "y += 1",
+            ErrorKind::NameError,
+            "'y' is an undefined variable!",
+        ),
+        (
+            "let x = 1\nx /= 0",
+            ErrorKind::DivisionByZero,
+            "Division by zero",
+        ),
+    ];
+    for (src, kind, message) in cases {
+        let err = run_and_capture_sludge_err(src);
+        assert_eq!((err.kind, err.message.as_str()), (kind, message), "{src}");
+    }
+}
+
+#[test]
+fn test_optional_chaining_and_null_coalescing() -> anyhow::Result<()> {
+    let out = run_and_capture(
+        r#"
+            let d = {"a": {"b": 1}}
+            print(d.get("x")?.get("b"), d.get("a")?.get("b"))
+            print(d.get("x") ?? 5, 0 ?? 5, null ?? null ?? 3, false ?? true)
+            let calls = [0]
+            fn bump() {
+                calls.push(1)
+                return 1
+            }
+            let missing = null
+            print(missing?.foo(bump()), 1 ?? bump(), calls.length())
+            print(d.get("x")?.keys ?? "none")
+        "#,
+    )?;
+    assert_eq!(out, "NULL 1\n5 0 3 false\nNULL 1 1\nnone\n");
+
+    let err = run_and_capture_sludge_err("let d = {}\nd.get(\"x\").keys");
+    assert_eq!(err.kind, ErrorKind::TypeError);
+    assert_eq!(
+        err.message,
+        "member access not supported: type 'null' has no members"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_lint_warns_on_unchecked_get() -> anyhow::Result<()> {
+    let program = parse_program(
+        r#"let d = {"a": 1}
+let a = d.get("a")
+print(a + 1)
+let b = d.get("b")
+if (b != null) {
+    print(b + 1)
+}
+let c = d.get("c") ?? 0
+print(c + 1, d.get("x").keys, d.get("y")?.keys)
+let e = d.get("e")
+e = 3
+let f = fn(a) { return a * 2 }
+print(e * 2, f(d.get("z")))"#,
+    )?;
+    let warnings: Vec<String> = lint(&program).iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        [
+            "3:7: warning: 'a' may be null (from `get`) when used in an operator; \
+             check it with `!= null`, or use `??` or `?.`",
+            "9:16: warning: the result of `get` may be null when used in a member access; \
+             use `??` or `?.`",
+        ]
+    );
+    Ok(())
 }
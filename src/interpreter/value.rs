@@ -1,19 +1,91 @@
 use crate::ast::*;
 use crate::interpreter::Interpreter;
+use crate::interpreter::error::{TraceFrame, raise, sludge_error};
+use crate::interpreter::signature::{NamedArgs, Signature};
 use crate::interpreter::variable_scope::VariableScope;
 
-use anyhow::{Error, anyhow};
-use serde::Serialize;
+use anyhow::{Error, bail};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::iter::Sum;
-use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Rem, Shl, Shr, Sub};
 use std::rc::Rc;
 
 pub trait BuiltinFn: std::fmt::Debug {
     fn call(&self, args: &[Value]) -> Result<Value, Error>;
+
+    /// Calls with `name: value` arguments as well. Only builtins that
+    /// declare a `Signature` accept them.
+    fn call_named(&self, args: &[Value], named: NamedArgs) -> Result<Value, Error> {
+        if let Some((name, _)) = named.first() {
+            raise!(ArityError, "unexpected named argument '{name}'");
+        }
+        self.call(args)
+    }
+
+    /// Calls from `interpreter`, for builtins that call back into script
+    /// functions, such as those made by `partial`.
+    fn call_in(
+        &self,
+        _interpreter: &Interpreter,
+        args: &[Value],
+        named: NamedArgs,
+    ) -> Result<Value, Error> {
+        self.call_named(args, named)
+    }
+}
+
+/// A lazily evaluated sequence of values, advanced one element at a time.
+pub trait ValueIterator: std::fmt::Debug {
+    /// Produces the next element, or `None` once the sequence is exhausted.
+    /// Elements pulled from other iterators are charged to `interpreter`'s
+    /// budget.
+    fn next(&mut self, interpreter: &Interpreter) -> Result<Option<Value>, Error>;
+}
+
+/// A host-defined object exposed to scripts as an opaque handle, such as a
+/// database connection. Scripts read its members and call its methods with
+/// `obj.field` and `obj.method(args)`.
+pub trait NativeObject: std::any::Any + std::fmt::Debug {
+    /// The name reported in error messages and by the default `Display`.
+    fn type_name(&self) -> &str;
+
+    /// Returns the value of a property. Names this does not answer are
+    /// treated as methods and dispatched to `call_method`.
+    fn get_member(&self, _field: &str) -> Option<Value> {
+        None
+    }
+
+    fn call_method(&self, method: &str, args: &[Value]) -> Result<Value, Error>;
+
+    fn fmt_display(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}>", self.type_name())
+    }
+
+    /// Compares against another native object; handles to the same object
+    /// are always equal. Implementations can downcast `other` through
+    /// `std::any::Any`.
+    fn equals(&self, _other: &dyn NativeObject) -> bool {
+        false
+    }
+}
+
+/// A method of a native object, bound to its receiver.
+#[derive(Debug)]
+pub struct NativeMethod {
+    pub object: Rc<dyn NativeObject>,
+    pub name: String,
+}
+
+impl BuiltinFn for NativeMethod {
+    fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        self.object.call_method(&self.name, args)
+    }
 }
 
 #[derive(Clone)]
@@ -68,9 +140,12 @@ pub enum Value {
     Boolean(bool),
     String(String),
     Function {
-        arguments: Vec<String>,
-        statement: Box<Expr>,
+        /// The declared name, or the `let` binding it was created for.
+        name: Option<String>,
+        signature: Rc<Signature>,
+        statement: Rc<Expr>,
         scope: Rc<VariableScope>,
+        generator: bool,
     },
     List {
         values: Rc<RefCell<Vec<Value>>>,
@@ -87,10 +162,24 @@ pub enum Value {
     Return {
         value: Box<Value>,
     },
+    /// A call in tail position, returned for the caller to run.
+    TailCall {
+        callable: Box<Value>,
+        args: Vec<Value>,
+        named: NamedArgs,
+        frame: TraceFrame,
+    },
     BuiltinFn(Rc<dyn BuiltinFn>),
+    Iterator(Rc<RefCell<dyn ValueIterator>>),
+    Module {
+        name: &'static str,
+        members: Rc<HashMap<&'static str, Value>>,
+    },
+    Native(Rc<dyn NativeObject>),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum Hashable {
     Null,
     Int32(i32),
@@ -118,7 +207,7 @@ impl TryFrom<Value> for Hashable {
             Value::Int32(i) => Ok(Hashable::Int32(i)),
             Value::Boolean(b) => Ok(Hashable::Boolean(b)),
             Value::String(s) => Ok(Hashable::String(s)),
-            _ => Err(anyhow!("invalid key")),
+            _ => Err(sludge_error!(TypeError, "invalid key")),
         }
     }
 }
@@ -132,15 +221,34 @@ impl TryFrom<&Value> for Hashable {
             Value::Int32(i) => Ok(Hashable::Int32(*i)),
             Value::Boolean(b) => Ok(Hashable::Boolean(*b)),
             Value::String(s) => Ok(Hashable::String(s.clone())),
-            _ => Err(anyhow!("invalid key")),
+            _ => Err(sludge_error!(TypeError, "invalid key")),
+        }
+    }
+}
+
+/// Writes `s` as a string literal, escaping what the grammar would not read
+/// back as written.
+fn write_literal(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\r' => write!(f, "\\r")?,
+            c => write!(f, "{c}")?,
         }
     }
+    write!(f, "\"")
 }
 
 impl std::fmt::Display for Hashable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Hashable::Null if f.alternate() => write!(f, "null"),
             Hashable::Null => write!(f, "NULL"),
+            Hashable::String(n) if f.alternate() => write_literal(f, n),
             Hashable::Int32(n) => write!(f, "{n}"),
             Hashable::Boolean(n) => write!(f, "{n}"),
             Hashable::String(n) => write!(f, "{n}"),
@@ -152,7 +260,7 @@ impl Value {
     pub fn to_bool(&self) -> Result<bool, Error> {
         match self {
             Value::Boolean(v) => Ok(*v),
-            v => Err(anyhow!("Expcted boolean got: {}", v)),
+            v => raise!(TypeError, "Expcted boolean got: {}", v),
         }
     }
 }
@@ -165,6 +273,7 @@ impl PartialEq for Value {
             (Int32(a), Int32(b)) => a == b,
             (Boolean(a), Boolean(b)) => a == b,
             (String(a), String(b)) => a == b,
+            (Native(a), Native(b)) => Rc::ptr_eq(a, b) || a.equals(b.as_ref()),
             _ => false,
         }
     }
@@ -185,29 +294,85 @@ impl PartialOrd for Value {
 impl Value {
     pub fn pow(self, exp: Value) -> Result<Value, Error> {
         match (self, exp) {
-            (Value::Int32(base), Value::Int32(exp)) => {
-                if exp < 0 {
-                    Err(anyhow!("Negative exponents not supported for Int32"))
-                } else {
-                    Ok(Value::Int32(base.pow(exp as u32)))
-                }
+            (Value::Int32(_), Value::Int32(exp)) if exp < 0 => {
+                raise!(ValueError, "Negative exponents not supported for int")
             }
-            (a, b) => Err(anyhow!("Cannot exponentiate {:?} by {:?}", a, b)),
+            (Value::Int32(base), Value::Int32(exp)) => match base.checked_pow(exp as u32) {
+                Some(n) => Ok(Value::Int32(n)),
+                None => raise!(Overflow, "integer overflow in {base} ** {exp}"),
+            },
+            (a, b) => raise!(
+                TypeError,
+                "Cannot exponentiate {} by {}",
+                Interpreter::type_name(&a),
+                Interpreter::type_name(&b)
+            ),
         }
     }
+
+    /// `item in self`: list and tuple elements (compared as by `==`), set
+    /// members, dictionary keys, or substrings of a string.
+    pub fn contains(&self, item: &Value) -> Result<Value, Error> {
+        let found = match (self, item) {
+            (Value::List { values }, _) => values.borrow().contains(item),
+            (Value::Tuple { values }, _) => values.contains(item),
+            (Value::Set { values }, _) => {
+                values.borrow().contains(&Hashable::try_from(item.clone())?)
+            }
+            (Value::Dictionary { values }, _) => values
+                .borrow()
+                .contains_key(&Hashable::try_from(item.clone())?),
+            (Value::String(s), Value::String(sub)) => s.contains(sub.as_str()),
+            (Value::String(_), other) => raise!(
+                TypeError,
+                "'in <string>' requires a string, got {}",
+                Interpreter::type_name(other)
+            ),
+            (other, _) => raise!(
+                TypeError,
+                "'in' not supported for type {}",
+                Interpreter::type_name(other)
+            ),
+        };
+        Ok(Value::Boolean(found))
+    }
+}
+
+/// Applies a checked int operation, raising `Overflow` when the result does
+/// not fit.
+fn checked(a: i32, b: i32, symbol: &str, op: fn(i32, i32) -> Option<i32>) -> Result<Value, Error> {
+    match op(a, b) {
+        Some(n) => Ok(Value::Int32(n)),
+        None => raise!(Overflow, "integer overflow in {a} {symbol} {b}"),
+    }
+}
+
+/// Applies an int shift, raising a `ValueError` for shift amounts outside
+/// 0..32.
+fn shift(a: i32, b: i32, symbol: &str, op: fn(i32, u32) -> Option<i32>) -> Result<Value, Error> {
+    match u32::try_from(b).ok().and_then(|b| op(a, b)) {
+        Some(n) => Ok(Value::Int32(n)),
+        None => raise!(ValueError, "shift amount out of range in {a} {symbol} {b}"),
+    }
+}
+
+/// Raises a `TypeError` for a binary operator applied to unsupported types.
+fn unsupported(operation: &str, a: &Value, b: &Value) -> Result<Value, Error> {
+    raise!(
+        TypeError,
+        "{operation} not supported between {} and {}",
+        Interpreter::type_name(a),
+        Interpreter::type_name(b)
+    )
 }
 
 impl Add for Value {
     type Output = Result<Value, Error>;
     fn add(self, rhs: Value) -> Self::Output {
         match (self, rhs) {
-            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a + b)),
+            (Value::Int32(a), Value::Int32(b)) => checked(a, b, "+", i32::checked_add),
             (Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
-            (a, b) => Err(anyhow!(
-                "Addition not supported between {:?} and {:?}",
-                a,
-                b
-            )),
+            (a, b) => unsupported("Addition", &a, &b),
         }
     }
 }
@@ -216,12 +381,8 @@ impl Sub for Value {
     type Output = Result<Value, Error>;
     fn sub(self, rhs: Value) -> Self::Output {
         match (self, rhs) {
-            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a - b)),
-            (a, b) => Err(anyhow!(
-                "Subtraction not supported between {:?} and {:?}",
-                a,
-                b
-            )),
+            (Value::Int32(a), Value::Int32(b)) => checked(a, b, "-", i32::checked_sub),
+            (a, b) => unsupported("Subtraction", &a, &b),
         }
     }
 }
@@ -264,12 +425,8 @@ impl Mul for Value {
     type Output = Result<Value, Error>;
     fn mul(self, rhs: Value) -> Self::Output {
         match (self, rhs) {
-            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a * b)),
-            (a, b) => Err(anyhow!(
-                "Multiplication not supported between {:?} and {:?}",
-                a,
-                b
-            )),
+            (Value::Int32(a), Value::Int32(b)) => checked(a, b, "*", i32::checked_mul),
+            (a, b) => unsupported("Multiplication", &a, &b),
         }
     }
 }
@@ -278,13 +435,9 @@ impl Div for Value {
     type Output = Result<Value, Error>;
     fn div(self, rhs: Value) -> Self::Output {
         match (self, rhs) {
-            (Value::Int32(_), Value::Int32(0)) => Err(anyhow!("Division by zero")),
-            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a / b)),
-            (a, b) => Err(anyhow!(
-                "Division not supported between {:?} and {:?}",
-                a,
-                b
-            )),
+            (Value::Int32(_), Value::Int32(0)) => raise!(DivisionByZero, "Division by zero"),
+            (Value::Int32(a), Value::Int32(b)) => checked(a, b, "/", i32::checked_div),
+            (a, b) => unsupported("Division", &a, &b),
         }
     }
 }
@@ -294,9 +447,64 @@ impl Rem for Value {
 
     fn rem(self, rhs: Value) -> Self::Output {
         match (self, rhs) {
-            (Value::Int32(_), Value::Int32(0)) => Err(anyhow!("Modulo by zero")),
-            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a % b)),
-            (a, b) => Err(anyhow!("Modulo not supported between {:?} and {:?}", a, b)),
+            (Value::Int32(_), Value::Int32(0)) => raise!(DivisionByZero, "Modulo by zero"),
+            (Value::Int32(a), Value::Int32(b)) => checked(a, b, "%", i32::checked_rem),
+            (a, b) => unsupported("Modulo", &a, &b),
+        }
+    }
+}
+
+impl BitAnd for Value {
+    type Output = Result<Value, Error>;
+
+    fn bitand(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a & b)),
+            (a, b) => unsupported("Bitwise and", &a, &b),
+        }
+    }
+}
+
+impl BitOr for Value {
+    type Output = Result<Value, Error>;
+
+    fn bitor(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a | b)),
+            (a, b) => unsupported("Bitwise or", &a, &b),
+        }
+    }
+}
+
+impl BitXor for Value {
+    type Output = Result<Value, Error>;
+
+    fn bitxor(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::Int32(a), Value::Int32(b)) => Ok(Value::Int32(a ^ b)),
+            (a, b) => unsupported("Bitwise xor", &a, &b),
+        }
+    }
+}
+
+impl Shl for Value {
+    type Output = Result<Value, Error>;
+
+    fn shl(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::Int32(a), Value::Int32(b)) => shift(a, b, "<<", i32::checked_shl),
+            (a, b) => unsupported("Left shift", &a, &b),
+        }
+    }
+}
+
+impl Shr for Value {
+    type Output = Result<Value, Error>;
+
+    fn shr(self, rhs: Value) -> Self::Output {
+        match (self, rhs) {
+            (Value::Int32(a), Value::Int32(b)) => shift(a, b, ">>", i32::checked_shr),
+            (a, b) => unsupported("Right shift", &a, &b),
         }
     }
 }
@@ -306,67 +514,286 @@ impl Neg for Value {
 
     fn neg(self) -> Self::Output {
         match self {
-            Value::Int32(a) => Ok(Value::Int32(-a)),
-            a => Err(anyhow!("Negation not supported for {:?}", a)),
+            Value::Int32(a) => match a.checked_neg() {
+                Some(n) => Ok(Value::Int32(n)),
+                None => raise!(Overflow, "integer overflow in -({a})"),
+            },
+            a => raise!(
+                TypeError,
+                "Negation not supported for {}",
+                Interpreter::type_name(&a)
+            ),
         }
     }
 }
 
+/// Formats a value for output. The alternate form (`{:#}`) renders values in
+/// Sludge literal syntax, so the output can be pasted back into source.
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let literal = f.alternate();
+        let show = |v: &dyn std::fmt::Display| {
+            if literal {
+                format!("{v:#}")
+            } else {
+                v.to_string()
+            }
+        };
+
         match self {
+            Value::Null if literal => write!(f, "null"),
             Value::Null => write!(f, "NULL"),
             Value::Int32(n) => write!(f, "{n}"),
             Value::Boolean(n) => write!(f, "{n}"),
+            Value::String(n) if literal => write_literal(f, n),
             Value::String(n) => write!(f, "{n}"),
             Value::List { values } => {
-                write!(
-                    f,
-                    "list({})",
-                    values
-                        .borrow()
-                        .iter()
-                        .map(|v| v.to_string())
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                )
+                let items = values
+                    .borrow()
+                    .iter()
+                    .map(|v| show(v))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                if literal {
+                    write!(f, "[{items}]")
+                } else {
+                    write!(f, "list({items})")
+                }
             }
             Value::Dictionary { values } => {
-                write!(
-                    f,
-                    "dict({})",
-                    values
-                        .borrow()
-                        .iter()
+                let items = values.borrow();
+                let items = items.iter();
+                if literal {
+                    let items = items
+                        .map(|(k, v)| format!("{k:#}: {v:#}"))
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    write!(f, "{{{items}}}")
+                } else {
+                    let items = items
                         .map(|(k, v)| format!("({k}, {v})"))
                         .collect::<Vec<String>>()
-                        .join(", ")
-                )
+                        .join(", ");
+                    write!(f, "dict({items})")
+                }
             }
             Value::Set { values } => {
-                write!(
-                    f,
-                    "set({})",
-                    values
-                        .borrow()
-                        .iter()
-                        .map(|k| format!("{k}"))
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                )
+                let items = values
+                    .borrow()
+                    .iter()
+                    .map(|k| show(k))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                if literal {
+                    write!(f, "#{{{items}}}")
+                } else {
+                    write!(f, "set({items})")
+                }
             }
             Value::Tuple { values } => {
-                write!(
-                    f,
-                    "tuple({})",
-                    values
-                        .iter()
-                        .map(|v| v.to_string())
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                )
+                let items = values
+                    .iter()
+                    .map(|v| show(v))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                if literal && values.len() == 1 {
+                    write!(f, "({items},)")
+                } else if literal {
+                    write!(f, "({items})")
+                } else {
+                    write!(f, "tuple({items})")
+                }
             }
+            Value::Iterator(_) => write!(f, "iterator"),
+            Value::Module { name, .. } => write!(f, "module({name})"),
+            Value::Native(object) => object.fmt_display(f),
+            Value::Function {
+                name, signature, ..
+            } => write!(
+                f,
+                "fn {}{signature}",
+                name.as_deref().unwrap_or("<anonymous>")
+            ),
             _ => Ok(()),
         }
     }
 }
+
+/// Sorts set members so serialized output is deterministic.
+fn sorted_members(values: &HashSet<Hashable>) -> Vec<&Hashable> {
+    let mut members = values.iter().collect::<Vec<_>>();
+    members.sort_by_key(|h| h.to_string());
+    members
+}
+
+/// Serializes data-only values. Lists, tuples and sets all become sequences
+/// and dictionaries become maps; functions, iterators and modules are
+/// rejected.
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Int32(i) => serializer.serialize_i32(*i),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::List { values } => serializer.collect_seq(values.borrow().iter()),
+            Value::Tuple { values } => serializer.collect_seq(values),
+            Value::Set { values } => {
+                let values = values.borrow();
+                let members = sorted_members(&values);
+                let mut seq = serializer.serialize_seq(Some(members.len()))?;
+                for member in members {
+                    seq.serialize_element(member)?;
+                }
+                seq.end()
+            }
+            Value::Dictionary { values } => {
+                let values = values.borrow();
+                let mut map = serializer.serialize_map(Some(values.len()))?;
+                for (k, v) in values.iter() {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Function { .. } | Value::BuiltinFn(_) => {
+                Err(ser::Error::custom("functions cannot be serialized"))
+            }
+            other => Err(ser::Error::custom(format!(
+                "value '{other}' cannot be serialized"
+            ))),
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "null, an int, a boolean, a string, a sequence or a map")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Value, E> {
+        i32::try_from(n)
+            .map(Value::Int32)
+            .map_err(|_| E::custom(format!("number {n} does not fit in an int")))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+        i32::try_from(n)
+            .map(Value::Int32)
+            .map_err(|_| E::custom(format!("number {n} does not fit in an int")))
+    }
+
+    /// Floats with no fractional part, such as `2.0` or `1e3`, are read as
+    /// ints; any other float is rejected, as there is no float value.
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Value, E> {
+        if n.fract() != 0.0 {
+            return Err(E::custom(format!(
+                "number {n} is not an integer; floats are not supported"
+            )));
+        }
+        if n < i32::MIN as f64 || n > i32::MAX as f64 {
+            return Err(E::custom(format!("number {n} does not fit in an int")));
+        }
+        Ok(Value::Int32(n as i32))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            values.push(v);
+        }
+        Ok(Value::List {
+            values: Rc::new(RefCell::new(values)),
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((k, v)) = map.next_entry::<Hashable, Value>()? {
+            values.insert(k, v);
+        }
+        Ok(Value::Dictionary {
+            values: Rc::new(RefCell::new(values)),
+        })
+    }
+}
+
+/// Deserializes data-only values. Sequences always become lists, so tuples
+/// and sets round-trip as lists.
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl Value {
+    /// Converts a JSON document into a value. Objects become dictionaries
+    /// and arrays become lists; non-integer numbers are rejected.
+    pub fn from_json(json: serde_json::Value) -> Result<Value, Error> {
+        Ok(Value::deserialize(json)?)
+    }
+
+    /// Converts a data-only value into a JSON document. Unlike plain
+    /// serialization, dictionary keys must be strings rather than being
+    /// coerced to them.
+    pub fn to_json(&self) -> Result<serde_json::Value, Error> {
+        match self {
+            Value::List { values } => Ok(serde_json::Value::Array(
+                values
+                    .borrow()
+                    .iter()
+                    .map(Value::to_json)
+                    .collect::<Result<_, _>>()?,
+            )),
+            Value::Tuple { values } => Ok(serde_json::Value::Array(
+                values
+                    .iter()
+                    .map(Value::to_json)
+                    .collect::<Result<_, _>>()?,
+            )),
+            Value::Dictionary { values } => Ok(serde_json::Value::Object(
+                values
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| match k {
+                        Hashable::String(k) => Ok((k.clone(), v.to_json()?)),
+                        other => bail!(
+                            "dictionary key {other:#} is not a string; JSON object keys must be strings"
+                        ),
+                    })
+                    .collect::<Result<_, Error>>()?,
+            )),
+            Value::Function { .. } | Value::BuiltinFn(_) => {
+                bail!("functions cannot be converted to JSON")
+            }
+            other => Ok(serde_json::to_value(other)?),
+        }
+    }
+}
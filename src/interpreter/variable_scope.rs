@@ -1,5 +1,4 @@
 use crate::interpreter::builtins;
-use crate::interpreter::value::NamedBuiltin;
 use crate::interpreter::value::Value;
 
 use std::cell::RefCell;
@@ -13,32 +12,23 @@ pub struct VariableScope {
 }
 
 impl VariableScope {
-    /// Create a new root scope.
+    /// Create a new root scope with every builtin module declared.
     pub fn new() -> Rc<Self> {
-        let new_list = Rc::new(NamedBuiltin {
-            name: "list",
-            this: Value::Null,
-            f: builtins::list::new,
-        });
-
-        let new_dict = Rc::new(NamedBuiltin {
-            name: "dict",
-            this: Value::Null,
-            f: builtins::dict::dict,
-        });
-
-        let new_set = Rc::new(NamedBuiltin {
-            name: "set",
-            this: Value::Null,
-            f: builtins::set::set,
-        });
+        let scope = Self::root();
+        builtins::register_core(&scope);
+        builtins::sys::register(&scope, Vec::new());
+        builtins::json::register(&scope);
+
+        // Filesystem and subprocess access is denied until a host grants it.
+        builtins::fs::register(&scope, builtins::fs::Permissions::default());
+        builtins::process::register(&scope, false);
+        scope
+    }
 
+    /// Create an empty root scope with no builtins declared.
+    pub fn root() -> Rc<Self> {
         Rc::new(Self {
-            variables: RefCell::new(HashMap::from([
-                (String::from("list"), Value::BuiltinFn(new_list)),
-                (String::from("dict"), Value::BuiltinFn(new_dict)),
-                (String::from("set"), Value::BuiltinFn(new_set)),
-            ])),
+            variables: RefCell::new(HashMap::new()),
             parent: None,
         })
     }
@@ -0,0 +1,12 @@
+pub mod ast;
+mod engine;
+pub mod interpreter;
+
+pub use crate::engine::{BuiltinModule, Engine, EngineBuilder};
+pub use crate::interpreter::builtins::fs::Permissions;
+pub use crate::interpreter::builtins::sys::Exit;
+pub use crate::interpreter::convert::{FromValue, HostFn, IntoHostResult, IntoValue};
+pub use crate::interpreter::error::{CaughtError, ErrorKind, SludgeError, TraceFrame};
+pub use crate::interpreter::limits::{CancellationHandle, LimitError, Limits};
+pub use crate::interpreter::signature::Signature;
+pub use crate::interpreter::value::{NativeObject, Value};
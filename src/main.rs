@@ -11,13 +11,10 @@ use std::{
 };
 use yansi::Paint;
 
-mod ast;
-mod interpreter;
-
-use crate::ast::Statement;
-use crate::ast::parser::{parse_program, parse_stmt, underline_error};
-use crate::interpreter::Interpreter;
-use crate::interpreter::variable_scope::VariableScope;
+use sludge::ast::Statement;
+use sludge::ast::lint::lint;
+use sludge::ast::parser::{parse_program, parse_stmt, underline_error};
+use sludge::{Engine, Exit, Permissions, SludgeError};
 
 #[derive(Parser, Debug)]
 #[command(name = "sludge", version, about = "Sludge language CLI")]
@@ -29,37 +26,106 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Parse a file and execute it
-    Run { file: PathBuf },
+    Run {
+        file: PathBuf,
+        /// Arguments passed to the program, available through `args()`
+        #[arg(last = true)]
+        args: Vec<String>,
+        /// Directories the program may read from (comma separated)
+        #[arg(long, value_delimiter = ',')]
+        allow_read: Vec<PathBuf>,
+        /// Directories the program may write to (comma separated)
+        #[arg(long, value_delimiter = ',')]
+        allow_write: Vec<PathBuf>,
+        /// Allow the program to run subprocesses through `exec` and `spawn`
+        #[arg(long)]
+        allow_run: bool,
+    },
     /// Start an interactive Read–Eval–Print loop
     Repl,
     /// Parse a file and print its AST as pretty JSON
     Ast { file: PathBuf },
+    /// Check a file for values from `dict.get` used without a null check
+    Lint { file: PathBuf },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Commands::Run { file } => run_file(&file),
-        Commands::Repl => run_repl(),
+        Commands::Run {
+            file,
+            args,
+            allow_read,
+            allow_write,
+            allow_run,
+        } => {
+            let permissions = Permissions::new(&allow_read, &allow_write)?;
+            let code = run_file(&file, args, permissions, allow_run)?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+            Ok(())
+        }
+        Commands::Repl => {
+            let code = run_repl()?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+            Ok(())
+        }
         Commands::Ast { file } => print_ast(&file),
+        Commands::Lint { file } => {
+            if !lint_file(&file)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
     }
 }
 
-fn run_file(path: &PathBuf) -> Result<()> {
+/// Runs a program, returning the status code it requested through `exit()`.
+fn run_file(
+    path: &PathBuf,
+    args: Vec<String>,
+    permissions: Permissions,
+    allow_run: bool,
+) -> Result<i32> {
     let contents = fs::read_to_string(path)
         .with_context(|| format!("Failed to read program file '{}'.", path.display()))?;
 
     let program = parse_program(&contents).map_err(|e| anyhow!("Parse error: {}", e))?;
 
     let writer = Rc::new(RefCell::new(BufWriter::new(std::io::stdout())));
-    let interpreter = Interpreter::new(VariableScope::new(), writer.clone());
+    let engine = Engine::builder()
+        .args(args)
+        .permissions(permissions)
+        .allow_run(allow_run)
+        .stdout(writer.clone())
+        .build();
+
+    let result = engine.run_program(&program);
+    writer.borrow_mut().flush().ok();
 
-    interpreter
-        .run_program(&program)
-        .map_err(|e| anyhow!("Runtime error: {}", e))?;
+    match result {
+        Ok(_) => Ok(0),
+        Err(e) => match Exit::find(&e) {
+            Some(exit) => Ok(exit.code),
+            None => Err(anyhow!(
+                "Runtime error: {}{}",
+                e,
+                traceback(&e, &path.display().to_string())
+            )),
+        },
+    }
+}
 
-    writer.borrow_mut().flush().ok();
-    Ok(())
+/// The traceback of a runtime error on the lines after its message, or
+/// nothing for errors without one.
+fn traceback(err: &anyhow::Error, source: &str) -> String {
+    match SludgeError::find(err) {
+        Some(error) => format!("\n{}", error.traceback(source)),
+        None => String::new(),
+    }
 }
 
 fn print_ast(path: &PathBuf) -> Result<()> {
@@ -75,18 +141,81 @@ fn print_ast(path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn run_repl() -> Result<()> {
+/// Prints the lint warnings for a program, returning whether it had none.
+fn lint_file(path: &PathBuf) -> Result<bool> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read program file '{}'.", path.display()))?;
+
+    let program = parse_program(&contents).map_err(|e| anyhow!("Parse error: {}", e))?;
+
+    let warnings = lint(&program);
+    for warning in &warnings {
+        eprintln!("{}:{}", path.display(), warning);
+    }
+    Ok(warnings.is_empty())
+}
+
+/// Runs the statements of one REPL line, printing the value of each
+/// expression statement. Like a block, the line's `fn` declarations are
+/// hoisted first. A failing statement is reported and the rest still run;
+/// returns the status code if one called `exit()`.
+fn eval_repl_line(engine: &Engine, stmts: &[Statement]) -> Option<i32> {
+    let interpreter = engine.interpreter();
+    // Each statement is a run of its own, with the full limits and no
+    // Ctrl-C left over from the prompt.
+    let reset = || {
+        interpreter.reset_budget();
+        engine.cancellation_handle().clear();
+    };
+    let report = |e: anyhow::Error| match Exit::find(&e) {
+        Some(exit) => Some(exit.code),
+        None => {
+            println!("Eval error: {e}{}", traceback(&e, "<repl>"));
+            None
+        }
+    };
+
+    reset();
+    if let Err(e) = interpreter.hoist(stmts) {
+        return report(e);
+    }
+    for st in stmts {
+        reset();
+        match interpreter.execute_statement(st) {
+            Ok(val) if matches!(st, Statement::Expression(_)) => println!("{val:#}"),
+            Ok(_) => {}
+            Err(e) => {
+                if let Some(code) = report(e) {
+                    return Some(code);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Runs the REPL, returning the status code requested through `exit()`.
+fn run_repl() -> Result<i32> {
     let mut rl = DefaultEditor::new()?;
     let writer = Rc::new(RefCell::new(BufWriter::new(std::io::stdout())));
-    let interpreter = Interpreter::new(VariableScope::new(), writer.clone());
+    let engine = Engine::builder().stdout(writer.clone()).build();
+
+    // Ctrl-C while a statement runs aborts it; earlier definitions survive.
+    // At the prompt readline sees Ctrl-C as a key press instead.
+    signal_hook::flag::register(
+        signal_hook::consts::SIGINT,
+        engine.cancellation_handle().flag().clone(),
+    )?;
     let prompt = Paint::cyan(">>> ").to_string();
 
+    let mut code = 0;
+
     println!(
         "{}",
         Paint::new("🛢️ Welcome to sludge REPL. Ctrl-D to exit, :help for commands.").bold()
     );
 
-    loop {
+    'repl: loop {
         let line = rl.readline(&prompt);
         match line {
             Ok(input) => {
@@ -96,20 +225,10 @@ fn run_repl() -> Result<()> {
                 }
                 match parse_stmt(trimmed) {
                     Ok(stmts) => {
-                        for st in stmts {
-                            match st? {
-                                Statement::Expression(e) => {
-                                    match interpreter.execute_statement(&Statement::Expression(e)) {
-                                        Ok(val) => println!("{val}"),
-                                        Err(e) => println!("Eval error: {e}"),
-                                    }
-                                }
-                                other => {
-                                    if let Err(e) = interpreter.execute_statement(&other) {
-                                        println!("Eval error: {e}");
-                                    }
-                                }
-                            }
+                        let stmts = stmts.into_iter().collect::<Result<Vec<_>>>()?;
+                        if let Some(exit) = eval_repl_line(&engine, &stmts) {
+                            code = exit;
+                            break 'repl;
                         }
                     }
                     Err(e) => {
@@ -132,5 +251,5 @@ fn run_repl() -> Result<()> {
 
     println!("Goodbye! 👋");
     writer.borrow_mut().flush().ok();
-    Ok(())
+    Ok(code)
 }